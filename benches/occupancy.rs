@@ -0,0 +1,86 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use snek::occupancy::{Cell, OccupancyGrid};
+
+const SIZES: [i32; 2] = [20, 200];
+
+// a snake winding back and forth over the bottom `fill` fraction of the board
+fn snake_cells(size: i32, fill: f32) -> Vec<(i32, i32)> {
+    let len = ((size * size) as f32 * fill) as i32;
+
+    (0..len)
+        .map(|i| {
+            let y = i / size;
+            let x = if y % 2 == 0 { i % size } else { size - 1 - i % size };
+            (x, y)
+        })
+        .collect()
+}
+
+fn filled_grid(size: i32, cells: &[(i32, i32)]) -> OccupancyGrid {
+    let mut grid = OccupancyGrid::new(size, size);
+    for (x, y) in cells {
+        grid.set(*x, *y, Cell::Snake);
+    }
+    grid
+}
+
+// one tick's worth of bookkeeping: is the next cell free, move the head in, move the tail out
+fn collision(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collision");
+
+    for size in SIZES {
+        let cells = snake_cells(size, 0.5);
+        let grid = filled_grid(size, &cells);
+        let target = (size / 2, size - 1);
+
+        group.bench_with_input(BenchmarkId::new("grid", size), &size, |b, _| {
+            let mut grid = grid.clone();
+            b.iter(|| {
+                let free = grid.is_free(black_box(target.0), black_box(target.1));
+                grid.set(target.0, target.1, Cell::Snake);
+                grid.set(target.0, target.1, Cell::Empty);
+                free
+            })
+        });
+
+        // what snake_movement used to do every tick
+        group.bench_with_input(BenchmarkId::new("vec_contains", size), &size, |b, _| {
+            b.iter(|| black_box(&cells).contains(black_box(&target)))
+        });
+    }
+
+    group.finish();
+}
+
+fn sample_free(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sample_free");
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for size in SIZES {
+        for fill in [0.5, 0.99] {
+            let cells = snake_cells(size, fill);
+            let grid = filled_grid(size, &cells);
+            let id = format!("{}/{}%", size, (fill * 100.0) as i32);
+
+            group.bench_function(BenchmarkId::new("grid", &id), |b| {
+                b.iter(|| grid.sample_free(&mut rng))
+            });
+
+            // what spawn_food used to do, keep rolling until a cell isn't in the snake
+            group.bench_function(BenchmarkId::new("rejection", &id), |b| {
+                b.iter(|| loop {
+                    let pos = (rng.gen_range(0..size), rng.gen_range(0..size));
+                    if !cells.contains(&pos) {
+                        break pos;
+                    }
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, collision, sample_free);
+criterion_main!(benches);
@@ -0,0 +1,316 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    layout::{position_translation, size_scaling},
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    theme::{apply_theme, theme_menu, Palette, Theme, Themes},
+    Food, FoodKind, SnakeHead,
+};
+
+// the slowest the game speed setting goes, in percent of normal
+pub const MIN_GAME_SPEED: u32 = 30;
+const GAME_SPEED_STEP: u32 = 10;
+
+// decorations go just behind or just in front of what they're on
+const OUTLINE_SIZE: f32 = 1.3;
+const OUTLINE_Z: f32 = -0.5;
+const MARKER_Z: f32 = 0.5;
+
+// colours that stay apart for the common kinds of colour blindness.
+// everything the food kinds, creatures and portals use comes from the okabe-ito set
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorAssist {
+    #[default]
+    Off,
+    // deuteranopia and protanopia, reds and greens run together
+    RedGreen,
+    // tritanopia, blues and yellows do
+    BlueYellow,
+}
+
+const ASSISTS: [ColorAssist; 3] = [ColorAssist::Off, ColorAssist::RedGreen, ColorAssist::BlueYellow];
+
+impl ColorAssist {
+    // what it's called in the language files
+    pub fn key(self) -> &'static str {
+        match self {
+            ColorAssist::Off => "assist.off",
+            ColorAssist::RedGreen => "assist.red_green",
+            ColorAssist::BlueYellow => "assist.blue_yellow",
+        }
+    }
+
+    fn cycle(self, step: i32) -> ColorAssist {
+        let count = ASSISTS.len() as i32;
+        let i = ASSISTS.iter().position(|assist| *assist == self).unwrap_or(0) as i32;
+
+        ASSISTS[(i + step).rem_euclid(count) as usize]
+    }
+}
+
+// the theme's own colours with the player's settings on top.
+// the see-through body is the hardest thing to make out, so both make it solid
+pub fn adjust_palette(palette: &Palette, config: &Config) -> Palette {
+    let mut palette = *palette;
+
+    match config.color_assist {
+        ColorAssist::Off => (),
+        ColorAssist::RedGreen => {
+            palette.food = Color::rgb(0.902, 0.624, 0.0);
+            palette.bonus_food = Color::rgb(0.337, 0.706, 0.914);
+            palette.mouse = Color::rgb(0.941, 0.894, 0.259);
+            palette.rival = Color::rgb(0.835, 0.369, 0.0);
+            palette.portal = Color::rgb(0.8, 0.475, 0.655);
+            palette.segment_tint.set_a(0.85);
+        }
+        ColorAssist::BlueYellow => {
+            palette.food = Color::rgb(0.835, 0.369, 0.0);
+            palette.bonus_food = Color::rgb(0.8, 0.475, 0.655);
+            palette.mouse = Color::rgb(0.9, 0.9, 0.9);
+            palette.rival = Color::rgb(0.0, 0.62, 0.451);
+            palette.portal = Color::rgb(0.337, 0.706, 0.914);
+            palette.segment_tint.set_a(0.85);
+        }
+    }
+
+    if config.high_contrast {
+        palette.background = Color::BLACK;
+        palette.outline = Color::WHITE;
+        palette.score_text = Color::WHITE;
+        palette.head_tint = Color::YELLOW;
+        palette.segment_tint = Color::WHITE;
+    }
+
+    palette
+}
+
+// puts the settings back on whenever they or the theme change. the theme menu swaps in
+// a fresh copy of the theme, so this goes after it and before that gets pushed out
+fn assist_palette(config: Res<Config>, themes: Res<Themes>, mut theme: ResMut<Theme>) {
+    if !config.is_changed() && !themes.is_changed() {
+        return;
+    }
+
+    let base = match themes.list.iter().find(|t| t.name == theme.name) {
+        Some(base) => base.palette,
+        None => return,
+    };
+
+    let palette = adjust_palette(&base, &config);
+
+    // only touched when it's different, so apply_theme isn't run for nothing
+    if palette != theme.palette {
+        theme.palette = palette;
+    }
+}
+
+// one piece of an outline or marker, in tiles of whatever it's on
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Piece {
+    offset: Vec2,
+    size: Vec2,
+    angle: f32,
+    outline: bool,
+}
+
+impl Piece {
+    fn outline() -> Self {
+        Piece {
+            offset: Vec2::ZERO,
+            size: Vec2::splat(OUTLINE_SIZE),
+            angle: 0.0,
+            outline: true,
+        }
+    }
+
+    fn mark(x: f32, y: f32, w: f32, h: f32, angle: f32) -> Self {
+        Piece {
+            offset: Vec2::new(x, y),
+            size: Vec2::new(w, h),
+            angle,
+            outline: false,
+        }
+    }
+}
+
+// a dot, a plus, a bar and a cross, so the kinds are told apart by shape and not just colour
+fn marker(kind: FoodKind) -> Vec<Piece> {
+    let quarter = std::f32::consts::FRAC_PI_4;
+
+    match kind {
+        FoodKind::Normal => vec![Piece::mark(0.0, 0.0, 0.3, 0.3, 0.0)],
+        FoodKind::Bonus => vec![
+            Piece::mark(0.0, 0.0, 0.6, 0.16, 0.0),
+            Piece::mark(0.0, 0.0, 0.16, 0.6, 0.0),
+        ],
+        FoodKind::Mouse => vec![Piece::mark(0.0, 0.0, 0.6, 0.16, 0.0)],
+        FoodKind::Custom { .. } => vec![
+            Piece::mark(0.0, 0.0, 0.6, 0.16, quarter),
+            Piece::mark(0.0, 0.0, 0.6, 0.16, -quarter),
+        ],
+    }
+}
+
+// a sprite that sits on top of (or under) another one and follows it around
+#[derive(Component)]
+pub struct Decoration {
+    target: Entity,
+    piece: Piece,
+}
+
+fn place(piece: &Piece, target: &Transform) -> Transform {
+    let z = ifelse!(piece.outline, OUTLINE_Z, MARKER_Z);
+
+    Transform {
+        translation: target.translation + (piece.offset * target.scale.truncate()).extend(z),
+        rotation: Quat::from_rotation_z(piece.angle),
+        scale: (piece.size * target.scale.truncate()).extend(1.0),
+    }
+}
+
+// outlines round the food and head for high contrast, and markers on the food.
+// what should be there is worked out fresh every frame and the sprites are matched up
+// against it, so eaten food, rewinds and settings changes all sort themselves out
+fn decorate(
+    mut commands: Commands,
+    config: Res<Config>,
+    theme: Res<Theme>,
+    food: Query<(Entity, &FoodKind, &Transform), (With<Food>, Without<Decoration>)>,
+    heads: Query<(Entity, &Transform), (With<SnakeHead>, Without<Decoration>)>,
+    mut decorations: Query<(Entity, &Decoration, &mut Transform, &mut Sprite)>,
+) {
+    let mut wanted = Vec::new();
+
+    for (ent, kind, transform) in food.iter() {
+        if config.high_contrast {
+            wanted.push((ent, Piece::outline(), *transform));
+        }
+
+        if config.food_markers {
+            for piece in marker(*kind) {
+                wanted.push((ent, piece, *transform));
+            }
+        }
+    }
+
+    if config.high_contrast {
+        for (ent, transform) in heads.iter() {
+            wanted.push((ent, Piece::outline(), *transform));
+        }
+    }
+
+    let color = |piece: &Piece| ifelse!(piece.outline, theme.palette.outline, theme.palette.background);
+
+    for (ent, decoration, mut transform, mut sprite) in decorations.iter_mut() {
+        let found = wanted
+            .iter()
+            .position(|(target, piece, _)| *target == decoration.target && *piece == decoration.piece);
+
+        match found {
+            Some(i) => {
+                let (_, piece, target) = wanted.swap_remove(i);
+                *transform = place(&piece, &target);
+                sprite.color = color(&piece);
+            }
+            None => commands.entity(ent).despawn(),
+        }
+    }
+
+    for (target, piece, target_transform) in wanted {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: color(&piece),
+                    ..default()
+                },
+                transform: place(&piece, &target_transform),
+                ..default()
+            })
+            .insert(Decoration { target, piece });
+    }
+}
+
+fn accessibility_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut config: ResMut<Config>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        match event {
+            MenuEvent::Adjust("color_assist", step) => {
+                config.color_assist = config.color_assist.cycle(*step)
+            }
+            MenuEvent::Activate("color_assist") => config.color_assist = config.color_assist.cycle(1),
+            MenuEvent::Adjust("high_contrast", _) | MenuEvent::Activate("high_contrast") => {
+                config.high_contrast = !config.high_contrast
+            }
+            MenuEvent::Adjust("food_markers", _) | MenuEvent::Activate("food_markers") => {
+                config.food_markers = !config.food_markers
+            }
+            MenuEvent::Adjust("game_speed", step) => {
+                config.game_speed = (config.game_speed as i64 + *step as i64 * GAME_SPEED_STEP as i64)
+                    .clamp(MIN_GAME_SPEED as i64, 100) as u32
+            }
+            // wraps back round to full speed from the slowest
+            MenuEvent::Activate("game_speed") => {
+                config.game_speed = ifelse!(
+                    config.game_speed <= MIN_GAME_SPEED,
+                    100,
+                    config.game_speed - GAME_SPEED_STEP
+                )
+            }
+            _ => (),
+        }
+    }
+
+    if menu.open {
+        let on = |on: bool| [("on", locale.on_off(on))];
+
+        entries.set(
+            MenuPage::Settings,
+            "color_assist",
+            50,
+            locale.format(
+                "settings.color_assist",
+                &[("palette", locale.get(config.color_assist.key()))],
+            ),
+        );
+        entries.set(
+            MenuPage::Settings,
+            "high_contrast",
+            51,
+            locale.format("settings.high_contrast", &on(config.high_contrast)),
+        );
+        entries.set(
+            MenuPage::Settings,
+            "food_markers",
+            52,
+            locale.format("settings.food_markers", &on(config.food_markers)),
+        );
+        entries.set(
+            MenuPage::Settings,
+            "game_speed",
+            53,
+            locale.format("settings.game_speed", &[("percent", config.game_speed.to_string())]),
+        );
+    }
+}
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(accessibility_menu)
+            .add_system(assist_palette.after(theme_menu).before(apply_theme))
+            // once everything's been moved into place for the frame
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                decorate.after(position_translation).after(size_scaling),
+            );
+    }
+}
@@ -0,0 +1,239 @@
+use bevy::{audio::AudioSink, prelude::*};
+
+use crate::{
+    config::Config,
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    tick::TickClock,
+    DeathEvent, DirectionPair, ScoredEvent, SnakeDirection, SnakeHead, SNAKE_STEP,
+};
+
+// how far one press of left/right moves a volume slider
+const VOLUME_STEP: f32 = 0.1;
+
+// keeps the music from getting silly when the snake is very fast or very slow
+const MIN_MUSIC_SPEED: f32 = 0.75;
+const MAX_MUSIC_SPEED: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sfx {
+    Eat,
+    Turn,
+    // nothing gives out power-ups yet, this is here for when something does
+    #[allow(dead_code)]
+    PowerUp,
+    Death,
+    Menu,
+}
+
+// anything can ask for a sound. if there's no audio device, nothing happens
+pub struct SoundEvent(pub Sfx);
+
+struct SoundHandles {
+    eat: Handle<AudioSource>,
+    turn: Handle<AudioSource>,
+    power_up: Handle<AudioSource>,
+    death: Handle<AudioSource>,
+    menu: Handle<AudioSource>,
+    music: Handle<AudioSource>,
+}
+
+impl SoundHandles {
+    fn get(&self, sfx: Sfx) -> &Handle<AudioSource> {
+        match sfx {
+            Sfx::Eat => &self.eat,
+            Sfx::Turn => &self.turn,
+            Sfx::PowerUp => &self.power_up,
+            Sfx::Death => &self.death,
+            Sfx::Menu => &self.menu,
+        }
+    }
+}
+
+// strong handle to the looping music, so its volume and speed can change while it plays
+#[derive(Default)]
+struct Music(Option<Handle<AudioSink>>);
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundHandles {
+        eat: asset_server.load("sounds/eat.wav"),
+        turn: asset_server.load("sounds/turn.wav"),
+        power_up: asset_server.load("sounds/powerup.wav"),
+        death: asset_server.load("sounds/death.wav"),
+        menu: asset_server.load("sounds/menu.wav"),
+        music: asset_server.load("sounds/music.wav"),
+    });
+}
+
+// audio is optional all the way down: no AudioPlugin (headless) means no Audio resource,
+// and bevy itself goes quiet when there's no output device
+fn start_music(
+    audio: Option<Res<Audio>>,
+    sinks: Option<Res<Assets<AudioSink>>>,
+    handles: Res<SoundHandles>,
+    config: Res<Config>,
+    mut music: ResMut<Music>,
+) {
+    if let (Some(audio), Some(sinks)) = (audio, sinks) {
+        let volume = config.master_volume * config.music_volume;
+        let sink = audio.play_with_settings(
+            handles.music.clone(),
+            PlaybackSettings::LOOP.with_volume(volume),
+        );
+
+        music.0 = Some(sinks.get_handle(sink));
+    }
+}
+
+fn update_music(
+    sinks: Option<Res<Assets<AudioSink>>>,
+    music: Res<Music>,
+    config: Res<Config>,
+    clock: Res<TickClock>,
+) {
+    let sink = match (sinks.as_ref(), music.0.as_ref()) {
+        (Some(sinks), Some(handle)) => sinks.get(handle),
+        _ => None,
+    };
+
+    if let Some(sink) = sink {
+        sink.set_volume(config.master_volume * config.music_volume);
+
+        let step = clock.step.as_secs_f64() * 1000.0;
+        let speed = ifelse!(
+            config.music_follows_speed && step > 0.0,
+            ((SNAKE_STEP / step) as f32).clamp(MIN_MUSIC_SPEED, MAX_MUSIC_SPEED),
+            1.0
+        );
+        sink.set_speed(speed);
+    }
+}
+
+fn play_sounds(
+    audio: Option<Res<Audio>>,
+    handles: Res<SoundHandles>,
+    config: Res<Config>,
+    mut reader: EventReader<SoundEvent>,
+) {
+    let volume = config.master_volume * config.sfx_volume;
+
+    for SoundEvent(sfx) in reader.iter() {
+        if let Some(audio) = audio.as_ref() {
+            audio.play_with_settings(
+                handles.get(*sfx).clone(),
+                PlaybackSettings::ONCE.with_volume(volume),
+            );
+        }
+    }
+}
+
+// turns gameplay and menu happenings into sounds
+fn game_sounds(
+    mut writer: EventWriter<SoundEvent>,
+    mut score_reader: EventReader<ScoredEvent>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut menu_reader: EventReader<MenuEvent>,
+    menu: Res<Menu>,
+    heads: Query<&DirectionPair, With<SnakeHead>>,
+    mut last_direction: Local<Option<SnakeDirection>>,
+) {
+    if score_reader.iter().next().is_some() {
+        writer.send(SoundEvent(Sfx::Eat));
+    }
+
+    if death_reader.iter().next().is_some() {
+        writer.send(SoundEvent(Sfx::Death));
+    }
+
+    // moving the cursor, opening and closing all change the menu
+    if menu_reader.iter().next().is_some() || (menu.is_changed() && !menu.is_added()) {
+        writer.send(SoundEvent(Sfx::Menu));
+    }
+
+    for dir in heads.iter() {
+        if dir.1 != SnakeDirection::Null && *last_direction != Some(dir.1) {
+            writer.send(SoundEvent(Sfx::Turn));
+        }
+
+        *last_direction = Some(dir.1);
+    }
+}
+
+fn volume_label(locale: &Locale, key: &str, volume: f32) -> String {
+    locale.format(key, &[("percent", format!("{:.0}", volume * 100.0))])
+}
+
+fn audio_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut config: ResMut<Config>,
+    locale: Res<Locale>,
+) {
+    let step = |volume: f32, step: i32| {
+        // rounding keeps repeated steps from drifting off the 10% marks
+        ((volume + step as f32 * VOLUME_STEP) * 10.0).round().clamp(0.0, 10.0) / 10.0
+    };
+
+    for event in reader.iter() {
+        match event {
+            MenuEvent::Adjust("master_volume", n) => {
+                config.master_volume = step(config.master_volume, *n)
+            }
+            MenuEvent::Adjust("sfx_volume", n) => config.sfx_volume = step(config.sfx_volume, *n),
+            MenuEvent::Adjust("music_volume", n) => {
+                config.music_volume = step(config.music_volume, *n)
+            }
+            MenuEvent::Adjust("music_follows_speed", _)
+            | MenuEvent::Activate("music_follows_speed") => {
+                config.music_follows_speed = !config.music_follows_speed
+            }
+            _ => (),
+        }
+    }
+
+    if menu.open {
+        entries.set(
+            MenuPage::Settings,
+            "master_volume",
+            0,
+            volume_label(&locale, "settings.master_volume", config.master_volume),
+        );
+        entries.set(
+            MenuPage::Settings,
+            "sfx_volume",
+            1,
+            volume_label(&locale, "settings.sfx_volume", config.sfx_volume),
+        );
+        entries.set(
+            MenuPage::Settings,
+            "music_volume",
+            2,
+            volume_label(&locale, "settings.music_volume", config.music_volume),
+        );
+        entries.set(
+            MenuPage::Settings,
+            "music_follows_speed",
+            3,
+            locale.format(
+                "settings.music_follows_speed",
+                &[("on", locale.on_off(config.music_follows_speed))],
+            ),
+        );
+    }
+}
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Music::default())
+            .add_event::<SoundEvent>()
+            .add_startup_system(load_sounds)
+            .add_startup_system_to_stage(StartupStage::PostStartup, start_music)
+            .add_system(audio_menu)
+            .add_system(update_music)
+            .add_system(game_sounds)
+            .add_system(play_sounds.after(game_sounds));
+    }
+}
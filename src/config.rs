@@ -0,0 +1,116 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+use crate::{accessibility::ColorAssist, modes::GameMode};
+
+const CONFIG_FILE: &str = "config.ron";
+
+/// player settings, saved next to the assets folder whenever they change.
+/// missing fields fall back to their defaults so old files keep loading
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    // speed the music up and down with the snake
+    pub music_follows_speed: bool,
+    pub theme: String,
+    // which language file the text comes from, by code
+    pub language: String,
+    // juice, each one separately so motion-sensitive players can pick
+    pub particles: bool,
+    pub grow_flash: bool,
+    pub screen_shake: bool,
+    pub speed_trail: bool,
+    // turns off the shake and the flashes, whatever the two above say
+    pub reduced_motion: bool,
+    // colours that stay apart for colour blind players, on top of the theme
+    pub color_assist: ColorAssist,
+    // black background, solid snake, and outlines round the head and food
+    pub high_contrast: bool,
+    // a shape on each kind of food, so it isn't all down to colour
+    pub food_markers: bool,
+    // in percent, for anyone who wants it slower than the usual start
+    pub game_speed: u32,
+    // the old look: the score in huge letters behind the board, as well as the hud
+    pub big_score: bool,
+    // what "new game" starts
+    pub mode: GameMode,
+    // which level file the levels mode plays, by name
+    pub level: String,
+    // and which puzzle the puzzle mode does
+    pub puzzle: String,
+    // how many ticks classic lets you rewind, 0 for none
+    pub rewind_ticks: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            master_volume: 0.8,
+            sfx_volume: 1.0,
+            music_volume: 0.5,
+            music_follows_speed: true,
+            theme: "Classic".to_string(),
+            language: "en".to_string(),
+            particles: true,
+            grow_flash: true,
+            screen_shake: true,
+            speed_trail: true,
+            reduced_motion: false,
+            color_assist: ColorAssist::Off,
+            high_contrast: false,
+            food_markers: false,
+            game_speed: 100,
+            big_score: false,
+            mode: GameMode::Classic,
+            level: String::new(),
+            puzzle: String::new(),
+            rewind_ticks: 100,
+        }
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    FileAssetIo::get_base_path().join(CONFIG_FILE)
+}
+
+// a missing file is a first run, a broken one gets a warning. either way, defaults
+pub fn load_config() -> Config {
+    let path = config_path();
+
+    match fs::read_to_string(&path) {
+        Ok(s) => ron::from_str(&s).unwrap_or_else(|e| {
+            warn!("couldn't read {}: {}, using defaults", path.display(), e);
+            Config::default()
+        }),
+        Err(_) => Config::default(),
+    }
+}
+
+fn save_config(config: Res<Config>) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+
+    let path = config_path();
+    let result = ron::ser::to_string_pretty(&*config, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|s| fs::write(&path, s).map_err(|e| e.to_string()));
+
+    if let Err(e) = result {
+        warn!("couldn't save {}: {}", path.display(), e);
+    }
+}
+
+/// loads the Config, and saves it whenever it changes
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config())
+            .add_system_to_stage(CoreStage::Last, save_config);
+    }
+}
@@ -0,0 +1,384 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::{
+    modes::{advance_round, Round, Rules},
+    occupancy::{Cell, OccupancyGrid},
+    spawn_food_piece,
+    theme::Theme,
+    tick::{TickStage, TickStageLabel},
+    DirectionPair, Food, FoodKind, Obstacle, Outline, Position, Size, SnakeDirection, SnakeHead, SpriteSheet,
+    SNAKE_SIZE,
+};
+
+// mice only get to move every this many ticks, or they'd never be caught
+const MOUSE_PACE: u64 = 2;
+
+// things besides the player that move around the board every tick.
+// everything about one is in here, so rewinds and saves can just copy it
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Creature {
+    // walks to each waypoint in turn, in straight lines, then back again.
+    // a block in the way, a wall as far as the grid's concerned
+    Patrol {
+        path: Vec<Position>,
+        // which waypoint it's heading for
+        #[serde(default)]
+        target: usize,
+        #[serde(default)]
+        backwards: bool,
+    },
+    // another snake, after the same food. head first
+    Rival {
+        body: VecDeque<Position>,
+        // segments it still owes itself from eating
+        #[serde(default)]
+        grow: u32,
+    },
+}
+
+impl Creature {
+    // where it starts out, when it comes from a level file
+    pub fn start(&self) -> Position {
+        match self {
+            Creature::Patrol { path, .. } => path.first().copied().unwrap_or(Position { x: 0, y: 0 }),
+            Creature::Rival { body, .. } => body.front().copied().unwrap_or(Position { x: 0, y: 0 }),
+        }
+    }
+
+    // every cell it's taking up on the grid
+    fn cells(&self, pos: Position) -> Vec<Position> {
+        match self {
+            Creature::Patrol { .. } => vec![pos],
+            Creature::Rival { body, .. } => body.iter().copied().collect(),
+        }
+    }
+}
+
+// the rest of a rival's body, one sprite per segment behind the head
+#[derive(Component, Default)]
+pub struct RivalSprites(Vec<Entity>);
+
+#[derive(Component)]
+pub struct RivalBody;
+
+fn spawn_rival_body(commands: &mut Commands, pos: Position, theme: &Theme) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: theme.palette.rival,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(RivalBody)
+        .insert(Size::square(SNAKE_SIZE * 0.8))
+        .insert(pos)
+        .id()
+}
+
+// puts a creature on the board and claims its cells
+pub fn spawn_creature(
+    commands: &mut Commands,
+    grid: &mut OccupancyGrid,
+    pos: Position,
+    creature: Creature,
+    theme: &Theme,
+) -> Entity {
+    let cell = ifelse!(matches!(creature, Creature::Patrol { .. }), Cell::Wall, Cell::Snake);
+
+    for cell_pos in creature.cells(pos) {
+        grid.set(cell_pos.x, cell_pos.y, cell);
+    }
+
+    match creature {
+        // drawn just like the walls, and recoloured with them
+        Creature::Patrol { .. } => commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: theme.palette.outline,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Outline)
+            .insert(Obstacle)
+            .insert(Size::square(1.0))
+            .insert(pos)
+            .insert(creature)
+            .id(),
+        Creature::Rival { ref body, .. } => {
+            let sprites = body
+                .iter()
+                .skip(1)
+                .map(|pos| spawn_rival_body(commands, *pos, theme))
+                .collect();
+
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: theme.palette.rival,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(Size::square(SNAKE_SIZE))
+                .insert(pos)
+                .insert(RivalSprites(sprites))
+                .insert(creature)
+                .id()
+        }
+    }
+}
+
+// takes a creature off the board again, cells and sprites and all
+pub fn despawn_creature(
+    commands: &mut Commands,
+    grid: &mut OccupancyGrid,
+    ent: Entity,
+    pos: Position,
+    creature: &Creature,
+    sprites: Option<&RivalSprites>,
+) {
+    for cell_pos in creature.cells(pos) {
+        grid.set(cell_pos.x, cell_pos.y, Cell::Empty);
+    }
+
+    for sprite in sprites.iter().flat_map(|sprites| sprites.0.iter()) {
+        commands.entity(*sprite).despawn();
+    }
+
+    commands.entity(ent).despawn();
+}
+
+// whatever a level puts on the board besides walls
+pub fn spawn_creatures(
+    commands: &mut Commands,
+    grid: &mut OccupancyGrid,
+    rules: &Rules,
+    sprite_sheet: &SpriteSheet,
+    theme: &Theme,
+) {
+    for creature in rules.creatures.iter() {
+        spawn_creature(commands, grid, creature.start(), creature.clone(), theme);
+    }
+
+    for pos in rules.mice.iter() {
+        grid.set(pos.x, pos.y, Cell::Food);
+        spawn_food_piece(commands, *pos, FoodKind::Mouse, sprite_sheet, theme);
+    }
+}
+
+const NEIGHBOURS: [SnakeDirection; 4] = [
+    SnakeDirection::Up,
+    SnakeDirection::Down,
+    SnakeDirection::Left,
+    SnakeDirection::Right,
+];
+
+fn distance(a: Position, b: Position) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+// first step on the shortest way to the nearest food, going around anything in the way.
+// with no food in reach, any free cell at all, and None when it's boxed in
+fn rival_step(grid: &OccupancyGrid, from: Position) -> Option<Position> {
+    let index = |pos: Position| (pos.y * grid.width() + pos.x) as usize;
+    let passable = |pos: Position| matches!(grid.get(pos.x, pos.y), Cell::Empty | Cell::Food);
+
+    // which first step each cell was reached by
+    let mut first = vec![None; (grid.width() * grid.height()).max(0) as usize];
+    let mut queue = VecDeque::new();
+
+    for dir in NEIGHBOURS {
+        let next = from.step(dir);
+
+        if passable(next) && first[index(next)].is_none() {
+            first[index(next)] = Some(next);
+            queue.push_back(next);
+        }
+    }
+
+    let fallback = queue.front().copied();
+
+    while let Some(pos) = queue.pop_front() {
+        if grid.get(pos.x, pos.y) == Cell::Food {
+            return first[index(pos)];
+        }
+
+        for dir in NEIGHBOURS {
+            let next = pos.step(dir);
+
+            if passable(next) && first[index(next)].is_none() {
+                first[index(next)] = first[index(pos)];
+                queue.push_back(next);
+            }
+        }
+    }
+
+    fallback
+}
+
+// everything moves in one go, in the same order every time, so replays and
+// dailies come out the same. runs before the snake does
+fn move_creatures(
+    round: Res<Round>,
+    mut grid: ResMut<OccupancyGrid>,
+    heads: Query<(&Position, &DirectionPair), (With<SnakeHead>, Without<Food>, Without<Creature>)>,
+    mut mice: Query<(&mut Position, &FoodKind), (With<Food>, Without<Creature>)>,
+    mut creatures: Query<(&mut Position, &mut Creature), Without<Food>>,
+) {
+    // everything waits for the player to get going, same as the clock
+    let head = match heads.iter().next() {
+        Some((pos, dir)) if dir.1 != SnakeDirection::Null => *pos,
+        _ => return,
+    };
+
+    // mice run from the head, as far as one step can take them
+    if round.ticks.is_multiple_of(MOUSE_PACE) {
+        for (mut pos, kind) in mice.iter_mut() {
+            if *kind != FoodKind::Mouse {
+                continue;
+            }
+
+            let best = NEIGHBOURS
+                .iter()
+                .map(|dir| pos.step(*dir))
+                .filter(|next| grid.get(next.x, next.y) == Cell::Empty)
+                .fold(*pos, |best, next| {
+                    ifelse!(distance(next, head) > distance(best, head), next, best)
+                });
+
+            if best != *pos {
+                grid.set(pos.x, pos.y, Cell::Empty);
+                grid.set(best.x, best.y, Cell::Food);
+                *pos = best;
+            }
+        }
+    }
+
+    for (mut pos, mut creature) in creatures.iter_mut() {
+        match &mut *creature {
+            Creature::Patrol {
+                path,
+                target,
+                backwards,
+            } => {
+                if path.len() < 2 {
+                    continue;
+                }
+
+                *target = (*target).min(path.len() - 1);
+
+                // turn around at either end
+                if *pos == path[*target] {
+                    if *backwards && *target == 0 {
+                        *backwards = false;
+                    } else if !*backwards && *target + 1 == path.len() {
+                        *backwards = true;
+                    }
+
+                    *target = ifelse!(*backwards, *target - 1, *target + 1);
+                }
+
+                let goal = path[*target];
+                let next = if goal.x != pos.x {
+                    Position { x: pos.x + (goal.x - pos.x).signum(), y: pos.y }
+                } else {
+                    Position { x: pos.x, y: pos.y + (goal.y - pos.y).signum() }
+                };
+
+                // waits for whatever's in the way to get out of it
+                if grid.get(next.x, next.y) == Cell::Empty {
+                    grid.set(pos.x, pos.y, Cell::Empty);
+                    grid.set(next.x, next.y, Cell::Wall);
+                    *pos = next;
+                }
+            }
+            Creature::Rival { body, grow } => {
+                let next = match body.front().and_then(|head| rival_step(&grid, *head)) {
+                    Some(next) => next,
+                    None => continue,
+                };
+
+                if *grow > 0 {
+                    *grow -= 1;
+                } else if let Some(tail) = body.pop_back() {
+                    grid.set(tail.x, tail.y, Cell::Empty);
+                }
+
+                // food stays on the board until collision_detection eats it
+                grid.set(next.x, next.y, Cell::Snake);
+                body.push_front(next);
+                *pos = next;
+            }
+        }
+    }
+}
+
+// keeps one sprite per rival segment, where the segment is
+fn draw_rivals(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    mut rivals: Query<(&Creature, &mut RivalSprites)>,
+    mut positions: Query<&mut Position, With<RivalBody>>,
+) {
+    for (creature, mut sprites) in rivals.iter_mut() {
+        let body = match creature {
+            Creature::Rival { body, .. } => body,
+            _ => continue,
+        };
+
+        let wanted = body.len().saturating_sub(1);
+
+        while sprites.0.len() > wanted {
+            if let Some(ent) = sprites.0.pop() {
+                commands.entity(ent).despawn();
+            }
+        }
+
+        for (i, pos) in body.iter().skip(1).enumerate() {
+            match sprites.0.get(i) {
+                Some(ent) => {
+                    if let Ok(mut sprite_pos) = positions.get_mut(*ent) {
+                        *sprite_pos = *pos;
+                    }
+                }
+                None => sprites.0.push(spawn_rival_body(&mut commands, *pos, &theme)),
+            }
+        }
+    }
+}
+
+fn recolor_rivals(
+    theme: Res<Theme>,
+    mut sprites: Query<&mut Sprite, Or<(With<RivalBody>, With<RivalSprites>)>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    for mut sprite in &mut sprites {
+        sprite.color = theme.palette.rival;
+    }
+}
+
+pub struct CreaturePlugin;
+
+impl Plugin for CreaturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(recolor_rivals)
+            .stage(TickStageLabel, |stage: &mut TickStage| {
+                stage.add_system_set(
+                    SystemSet::new()
+                        .with_system(
+                            move_creatures
+                                .after(advance_round)
+                                .before(crate::GameSystem::Move),
+                        )
+                        .with_system(draw_rivals.after(move_creatures)),
+                )
+            });
+    }
+}
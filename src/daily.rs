@@ -0,0 +1,292 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    modes::{GameMode, Replay, Round, Rules},
+    rng::GameRng,
+    GameOver, Position, Score,
+};
+
+const DAILY_FILE: &str = "daily.ron";
+
+// mixed into the date so the daily seed isn't just the day number
+const DAILY_SALT: u64 = 0x5e4e_6b00_da17_0000;
+
+// at most this many little walls on a daily board
+const MAX_DAILY_WALLS: u32 = 6;
+
+// how far back the history page goes
+const HISTORY_IDS: [&str; 7] = [
+    "history_0",
+    "history_1",
+    "history_2",
+    "history_3",
+    "history_4",
+    "history_5",
+    "history_6",
+];
+
+// a calendar day, in UTC so everyone's on the same one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    // days since 1970-01-01 to a date and back, after howard hinnant's civil calendar algorithms
+    pub fn from_days(days: i64) -> Date {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = ifelse!(mp < 10, mp + 3, mp - 9) as u32;
+
+        Date {
+            year: yoe + era * 400 + ifelse!(month <= 2, 1, 0),
+            month,
+            day,
+        }
+    }
+
+    pub fn days(self) -> i64 {
+        let year = self.year - ifelse!(self.month <= 2, 1, 0);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = self.month as i64;
+        let doy = (153 * ifelse!(month > 2, month - 3, month + 9) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn today() -> Date {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+
+        Date::from_days((secs / 86_400) as i64)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// the snake starts in the bottom left, walls stay out of its way there
+fn near_start(pos: Position) -> bool {
+    pos.x <= 6 && pos.y <= 6
+}
+
+// everything about a day's board comes out of the date and nothing else:
+// its size, a few short walls, how much bonus food there is, and the food seed
+pub fn daily_rules(date: Date) -> (u64, Rules) {
+    let mut rng = GameRng::new(date.days() as u64 ^ DAILY_SALT);
+
+    let width = rng.gen_range(8..=12) * 2;
+    let height = rng.gen_range(8..=12) * 2;
+    let bonus_chance = rng.gen_range(0..=3) as f64 / 10.0;
+
+    let mut walls = Vec::new();
+
+    for _ in 0..rng.gen_range(0..=MAX_DAILY_WALLS) {
+        let (dx, dy) = ifelse!(rng.gen_bool(0.5), (1, 0), (0, 1));
+        let length = rng.gen_range(2..=5);
+        let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+
+        for i in 0..length {
+            let pos = Position {
+                x: x + dx * i,
+                y: y + dy * i,
+            };
+
+            if pos.x < width && pos.y < height && !near_start(pos) && !walls.contains(&pos) {
+                walls.push(pos);
+            }
+        }
+    }
+
+    let rules = Rules {
+        width,
+        height,
+        walls,
+        bonus_chance,
+        ..default()
+    };
+
+    (rng.next_u64(), rules)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DailyResult {
+    pub score: u128,
+    pub replay: Replay,
+}
+
+// the best go at each day's board, with the replay of it. kept next to the config
+#[derive(Default)]
+pub struct DailyResults {
+    results: BTreeMap<String, DailyResult>,
+    // nothing's saved without a path, so tests can't clobber the real file
+    path: Option<PathBuf>,
+}
+
+impl DailyResults {
+    pub fn get(&self, date: Date) -> Option<&DailyResult> {
+        self.results.get(&date.to_string())
+    }
+
+    // keeps whichever of the day's tries was better. true if this one was
+    pub fn submit(&mut self, date: Date, score: u128, replay: &Replay) -> bool {
+        if self.get(date).is_some_and(|best| best.score >= score) {
+            return false;
+        }
+
+        self.results.insert(
+            date.to_string(),
+            DailyResult {
+                score,
+                replay: replay.clone(),
+            },
+        );
+        true
+    }
+
+    // days in a row with a daily played. today not being done yet doesn't break it
+    pub fn streak(&self, today: Date) -> u32 {
+        let start = ifelse!(self.get(today).is_some(), today.days(), today.days() - 1);
+
+        (0..)
+            .take_while(|i| self.get(Date::from_days(start - i)).is_some())
+            .count() as u32
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let result = ron::ser::to_string_pretty(&self.results, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|s| fs::write(path, s).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            warn!("couldn't save {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn load_daily_results() -> DailyResults {
+    let path = FileAssetIo::get_base_path().join(DAILY_FILE);
+
+    let results = match fs::read_to_string(&path) {
+        Ok(s) => ron::from_str(&s).unwrap_or_else(|e| {
+            warn!("couldn't read {}: {}, starting fresh", path.display(), e);
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    };
+
+    DailyResults {
+        results,
+        path: Some(path),
+    }
+}
+
+fn record_daily(
+    game_over: Res<GameOver>,
+    round: Res<Round>,
+    score: Res<Score>,
+    replay: Res<Replay>,
+    mut results: ResMut<DailyResults>,
+) {
+    if !game_over.is_changed() || game_over.0.is_none() || round.mode != GameMode::Daily {
+        return;
+    }
+
+    if let Some(date) = round.date {
+        if results.submit(date, score.0, &replay) {
+            results.save();
+        }
+    }
+}
+
+fn daily_menu(
+    mut menu: ResMut<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    results: Res<DailyResults>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        match event {
+            MenuEvent::Activate("daily_history") => menu.show(MenuPage::History),
+            MenuEvent::Activate("history_back") => menu.show(MenuPage::Main),
+            _ => (),
+        }
+    }
+
+    if !menu.open {
+        return;
+    }
+
+    let today = Date::today();
+    let streak = results.streak(today);
+
+    entries.set(MenuPage::Main, "daily_history", 30, locale.get("menu.daily_history"));
+    entries.set(
+        MenuPage::History,
+        "daily_streak",
+        0,
+        locale.format(
+            ifelse!(streak == 1, "daily.streak_one", "daily.streak_other"),
+            &[("days", streak.to_string())],
+        ),
+    );
+
+    for (i, id) in HISTORY_IDS.iter().enumerate() {
+        let date = Date::from_days(today.days() - i as i64);
+        let score = results
+            .get(date)
+            .map_or("-".to_string(), |result| result.score.to_string());
+
+        entries.set(
+            MenuPage::History,
+            id,
+            1 + i as i32,
+            format!("{}: {}", date, score),
+        );
+    }
+
+    entries.set(MenuPage::History, "history_back", 1000, locale.get("common.back"));
+}
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own, same as the high scores
+        if !app.world.contains_resource::<DailyResults>() {
+            app.insert_resource(load_daily_results());
+        }
+
+        app.add_system(daily_menu)
+            .add_system_to_stage(CoreStage::Last, record_daily);
+    }
+}
@@ -0,0 +1,309 @@
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::{
+    config::Config,
+    layout::BoardLayout,
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    theme::Theme,
+    tick::TickClock,
+    DeathEvent, Position, ScoredEvent, SnakeHead, SnakeSegments,
+};
+
+// particles per food eaten, and how they fly. speeds and sizes are in tiles
+const BURST_COUNT: usize = 12;
+const BURST_SPEED: f32 = 6.0;
+const BURST_LIFE: f32 = 0.4;
+const PARTICLE_SIZE: f32 = 0.2;
+
+const FLASH_TIME: f32 = 0.3;
+
+const SHAKE_TIME: f32 = 0.5;
+const SHAKE_STRENGTH: f32 = 0.5;
+
+// the trail shows up once a step is quicker than this many ms
+const TRAIL_STEP: f64 = 90.0;
+const TRAIL_LIFE: f32 = 0.25;
+const TRAIL_ALPHA: f32 = 0.3;
+
+// particles sit above the board, trails just under the snake
+const PARTICLE_Z: f32 = 20.0;
+const TRAIL_Z: f32 = 9.0;
+
+#[derive(Component)]
+pub struct MainCamera;
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    life: Timer,
+}
+
+// a sprite that fades out and goes away, used by the trail
+#[derive(Component)]
+struct Fade(Timer);
+
+#[derive(Component)]
+struct Flash(Timer);
+
+#[derive(Component)]
+struct Shake(Timer);
+
+fn burst_particles(
+    mut commands: Commands,
+    mut score_reader: EventReader<ScoredEvent>,
+    config: Res<Config>,
+    layout: Res<BoardLayout>,
+    theme: Res<Theme>,
+) {
+    let mut rng = thread_rng();
+
+    for ScoredEvent(pos, _) in score_reader.iter() {
+        if !config.particles {
+            continue;
+        }
+
+        let origin = layout.cell_center(*pos);
+
+        for _ in 0..BURST_COUNT {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(0.3..1.0) * BURST_SPEED * layout.tile_size;
+
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: theme.palette.food,
+                        ..default()
+                    },
+                    transform: Transform {
+                        translation: origin.extend(PARTICLE_Z),
+                        scale: Vec3::splat(PARTICLE_SIZE * layout.tile_size),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    life: Timer::from_seconds(BURST_LIFE, false),
+                });
+        }
+    }
+}
+
+fn move_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (ent, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        if particle.life.tick(time.delta()).finished() {
+            commands.entity(ent).despawn();
+            continue;
+        }
+
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        sprite.color.set_a(particle.life.percent_left());
+    }
+}
+
+// the segment that was just added lights up and fades back to normal
+fn start_flash(
+    mut commands: Commands,
+    mut score_reader: EventReader<ScoredEvent>,
+    segments: Res<SnakeSegments>,
+    config: Res<Config>,
+) {
+    if score_reader.iter().next().is_some() && config.grow_flash && !config.reduced_motion {
+        if let Some(tail) = segments.back() {
+            commands
+                .entity(*tail)
+                .insert(Flash(Timer::from_seconds(FLASH_TIME, false)));
+        }
+    }
+}
+
+fn flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    mut flashing: Query<(Entity, &mut Flash, &mut TextureAtlasSprite)>,
+) {
+    let tint = theme.palette.segment_tint;
+
+    for (ent, mut flash, mut sprite) in flashing.iter_mut() {
+        let t = flash.0.tick(time.delta()).percent_left();
+
+        sprite.color = Color::rgba(
+            tint.r() + (1.0 - tint.r()) * t,
+            tint.g() + (1.0 - tint.g()) * t,
+            tint.b() + (1.0 - tint.b()) * t,
+            tint.a() + (1.0 - tint.a()) * t,
+        );
+
+        if flash.0.finished() {
+            commands.entity(ent).remove::<Flash>();
+        }
+    }
+}
+
+fn start_shake(
+    mut commands: Commands,
+    mut death_reader: EventReader<DeathEvent>,
+    config: Res<Config>,
+    cameras: Query<Entity, With<MainCamera>>,
+) {
+    if death_reader.iter().next().is_some() && config.screen_shake && !config.reduced_motion {
+        for camera in cameras.iter() {
+            commands
+                .entity(camera)
+                .insert(Shake(Timer::from_seconds(SHAKE_TIME, false)));
+        }
+    }
+}
+
+// jiggles the camera around the middle of the board, dying down as the timer runs out
+fn shake(
+    mut commands: Commands,
+    time: Res<Time>,
+    layout: Res<BoardLayout>,
+    mut cameras: Query<(Entity, &mut Shake, &mut Transform), With<MainCamera>>,
+) {
+    let mut rng = thread_rng();
+
+    for (ent, mut shake, mut transform) in cameras.iter_mut() {
+        let strength = shake.0.tick(time.delta()).percent_left() * SHAKE_STRENGTH;
+        let offset = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+            * strength
+            * layout.tile_size;
+
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+
+        if shake.0.finished() {
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+            commands.entity(ent).remove::<Shake>();
+        }
+    }
+}
+
+// at high speed, the head leaves fading copies of itself behind
+fn trail(
+    mut commands: Commands,
+    config: Res<Config>,
+    layout: Res<BoardLayout>,
+    clock: Res<TickClock>,
+    heads: Query<
+        (&Position, &TextureAtlasSprite, &Handle<TextureAtlas>, &Transform),
+        (With<SnakeHead>, Changed<Position>),
+    >,
+    mut last_position: Local<Option<Vec2>>,
+) {
+    let fast = clock.step.as_secs_f64() * 1000.0 < TRAIL_STEP;
+
+    for (pos, sprite, atlas, transform) in heads.iter() {
+        if let Some(last) = *last_position {
+            if config.speed_trail && fast {
+                let mut color = sprite.color;
+                color.set_a(TRAIL_ALPHA);
+
+                commands
+                    .spawn_bundle(SpriteSheetBundle {
+                        sprite: TextureAtlasSprite {
+                            index: sprite.index,
+                            color,
+                            custom_size: sprite.custom_size,
+                            ..default()
+                        },
+                        texture_atlas: atlas.clone(),
+                        transform: Transform {
+                            translation: last.extend(TRAIL_Z),
+                            scale: transform.scale,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(Fade(Timer::from_seconds(TRAIL_LIFE, false)));
+            }
+        }
+
+        *last_position = Some(layout.cell_center(*pos));
+    }
+}
+
+fn fade(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fading: Query<(Entity, &mut Fade, &mut TextureAtlasSprite)>,
+) {
+    for (ent, mut fade, mut sprite) in fading.iter_mut() {
+        if fade.0.tick(time.delta()).finished() {
+            commands.entity(ent).despawn();
+        } else {
+            sprite.color.set_a(TRAIL_ALPHA * fade.0.percent_left());
+        }
+    }
+}
+
+fn effects_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut config: ResMut<Config>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        let id = match event {
+            MenuEvent::Adjust(id, _) | MenuEvent::Activate(id) => *id,
+        };
+
+        match id {
+            "particles" => config.particles = !config.particles,
+            "grow_flash" => config.grow_flash = !config.grow_flash,
+            "screen_shake" => config.screen_shake = !config.screen_shake,
+            "speed_trail" => config.speed_trail = !config.speed_trail,
+            "reduced_motion" => config.reduced_motion = !config.reduced_motion,
+            _ => (),
+        }
+    }
+
+    if menu.open {
+        let toggles = [
+            ("particles", "settings.particles", config.particles),
+            ("grow_flash", "settings.grow_flash", config.grow_flash),
+            ("screen_shake", "settings.screen_shake", config.screen_shake),
+            ("speed_trail", "settings.speed_trail", config.speed_trail),
+            ("reduced_motion", "settings.reduced_motion", config.reduced_motion),
+        ];
+
+        for (i, (id, key, on)) in toggles.into_iter().enumerate() {
+            entries.set(
+                MenuPage::Settings,
+                id,
+                20 + i as i32,
+                locale.format(key, &[("on", locale.on_off(on))]),
+            );
+        }
+    }
+}
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        // after the gameplay stage, so new segments and despawned food have settled
+        app.add_system(effects_menu).add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            SystemSet::new()
+                .with_system(burst_particles)
+                .with_system(move_particles)
+                .with_system(start_flash)
+                .with_system(flash.after(start_flash))
+                .with_system(start_shake)
+                .with_system(shake.after(start_shake))
+                .with_system(trail)
+                .with_system(fade),
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use bevy::{prelude::*, ui::UiSystem};
+use std::time::Duration;
+
+use crate::{
+    config::Config,
+    layout::{update_layout, BoardLayout},
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    modes::{HighScores, NewGameEvent, Round},
+    theme::Theme,
+    tick::TickClock,
+    Score, ScoreText, SnakeSegments,
+};
+
+// hud text is sized off the band it's in, which is sized off the window
+const HUD_FONT_BANDS: f32 = 0.45;
+const HUD_MARGIN_BANDS: f32 = 0.4;
+
+/// something the snake has for a while, listed in the hud until it runs out.
+/// nothing in the game hands these out yet, they're for other crates and scripts
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerUp {
+    pub name: String,
+    /// when it runs out, in the round's time, which stops while the game's paused
+    pub until: Duration,
+}
+
+/// whatever power-ups are going right now. a new game clears them
+#[derive(Debug, Clone, Default, Deref, DerefMut)]
+pub struct PowerUps(pub Vec<PowerUp>);
+
+// one bit of text in the hud, and what it shows
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HudItem {
+    Score,
+    Mode,
+    Best,
+    Length,
+    Speed,
+    PowerUps,
+    Time,
+}
+
+// one of the two rows, above and below the board
+#[derive(Component)]
+struct HudBand;
+
+// m:ss
+fn clock_time(secs: u64) -> String {
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn setup_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    theme: Res<Theme>,
+) {
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        // resized to fit the window by style_hud
+        font_size: 20.0,
+        color: theme.palette.score_text,
+    };
+
+    let rows = [
+        (
+            UiRect {
+                top: Val::Px(0.0),
+                ..default()
+            },
+            vec![HudItem::Score, HudItem::Mode, HudItem::Best],
+        ),
+        (
+            UiRect {
+                bottom: Val::Px(0.0),
+                ..default()
+            },
+            vec![HudItem::Length, HudItem::Speed, HudItem::PowerUps, HudItem::Time],
+        ),
+    ];
+
+    for (position, items) in rows {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.0),
+                        ..position
+                    },
+                    size: Size::new(Val::Percent(100.0), Val::Px(0.0)),
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                color: UiColor(Color::NONE),
+                ..default()
+            })
+            .insert(HudBand)
+            .with_children(|band| {
+                for item in items {
+                    band.spawn_bundle(TextBundle::from_section("", style.clone()))
+                        .insert(item);
+                }
+            });
+    }
+}
+
+fn update_hud(
+    mut items: Query<(&HudItem, &mut Text)>,
+    score: Res<Score>,
+    round: Res<Round>,
+    high_scores: Res<HighScores>,
+    segments: Res<SnakeSegments>,
+    clock: Res<TickClock>,
+    power_ups: Res<PowerUps>,
+    locale: Res<Locale>,
+) {
+    for (item, mut text) in &mut items {
+        let value = match item {
+            HudItem::Score => locale.format("hud.score", &[("score", score.0.to_string())]),
+            HudItem::Mode => round.mode.label(&locale),
+            // the best counts this game as it goes, so it's never behind the score
+            HudItem::Best => locale.format(
+                "hud.best",
+                &[("best", high_scores.best(&round.score_key()).max(score.0).to_string())],
+            ),
+            HudItem::Length => locale.format("hud.length", &[("length", segments.len().to_string())]),
+            HudItem::Speed => locale.format(
+                "hud.speed",
+                &[("speed", format!("{:.1}", 1.0 / clock.step.as_secs_f32()))],
+            ),
+            HudItem::PowerUps => power_ups
+                .iter()
+                .map(|power_up| {
+                    let left = power_up.until.saturating_sub(round.elapsed);
+                    let secs = left.as_secs() + ifelse!(left.subsec_nanos() > 0, 1, 0);
+
+                    locale.format(
+                        "hud.power_up",
+                        &[("name", power_up.name.clone()), ("secs", secs.to_string())],
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("  "),
+            HudItem::Time => {
+                // rounded up, so it says 0:00 exactly when time's up
+                let time = match round.time_left() {
+                    Some(left) => locale.format(
+                        "hud.time_left",
+                        &[(
+                            "time",
+                            clock_time(left.as_secs() + ifelse!(left.subsec_nanos() > 0, 1, 0)),
+                        )],
+                    ),
+                    None => clock_time(round.elapsed.as_secs()),
+                };
+
+                match round.moves_left() {
+                    Some(moves) => format!(
+                        "{}  {}",
+                        time,
+                        locale.format("hud.moves", &[("moves", moves.to_string())])
+                    ),
+                    None => time,
+                }
+            }
+        };
+
+        // only touched when it's different, so the ui isn't laid out again for nothing
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+// sizes and colours, whenever the window or the theme change
+fn style_hud(
+    layout: Res<BoardLayout>,
+    theme: Res<Theme>,
+    mut bands: Query<&mut Style, With<HudBand>>,
+    mut items: Query<&mut Text, With<HudItem>>,
+) {
+    if !layout.is_changed() && !theme.is_changed() {
+        return;
+    }
+
+    for mut style in &mut bands {
+        let margin = Val::Px(HUD_MARGIN_BANDS * layout.hud_band);
+
+        style.size.height = Val::Px(layout.hud_band);
+        style.padding = UiRect {
+            left: margin,
+            right: margin,
+            ..default()
+        };
+    }
+
+    for mut text in &mut items {
+        for section in text.sections.iter_mut() {
+            section.style.font_size = HUD_FONT_BANDS * layout.hud_band;
+            section.style.color = theme.palette.score_text;
+        }
+    }
+}
+
+fn show_big_score(config: Res<Config>, mut query: Query<&mut Visibility, With<ScoreText>>) {
+    if !config.is_changed() {
+        return;
+    }
+
+    for mut visibility in &mut query {
+        visibility.is_visible = config.big_score;
+    }
+}
+
+// run out ones go, and a new game starts with none
+fn expire_power_ups(
+    mut reader: EventReader<NewGameEvent>,
+    round: Res<Round>,
+    mut power_ups: ResMut<PowerUps>,
+) {
+    if reader.iter().last().is_some() {
+        power_ups.clear();
+    }
+
+    if power_ups.iter().any(|power_up| power_up.until <= round.elapsed) {
+        power_ups.retain(|power_up| power_up.until > round.elapsed);
+    }
+}
+
+fn hud_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut config: ResMut<Config>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        if let MenuEvent::Adjust("big_score", _) | MenuEvent::Activate("big_score") = event {
+            config.big_score = !config.big_score;
+        }
+    }
+
+    if menu.open {
+        entries.set(
+            MenuPage::Settings,
+            "big_score",
+            45,
+            locale.format("settings.big_score", &[("on", locale.on_off(config.big_score))]),
+        );
+    }
+}
+
+/// the score, best, length, speed, time, power-ups and mode, in bevy ui
+/// above and below the board. the big score behind the board is a setting
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PowerUps::default())
+            .add_startup_system(setup_hud)
+            .add_system(hud_menu)
+            .add_system(expire_power_ups)
+            .add_system(update_hud.after(expire_power_ups))
+            .add_system(show_big_score)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                style_hud.after(update_layout).before(UiSystem::Flex),
+            );
+    }
+}
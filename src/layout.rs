@@ -0,0 +1,188 @@
+use bevy::{prelude::*, window::WindowMode};
+
+use crate::{
+    occupancy::OccupancyGrid, theme::Theme, Position, ScoreText, Size, GRID_HEIGHT, GRID_WIDTH,
+    PADDING,
+};
+
+// the score text is sized in tiles so it grows and shrinks with the board
+const SCORE_FONT_TILES: f32 = 12.5;
+// the time attack countdown underneath it
+const COUNTDOWN_FONT_TILES: f32 = 4.0;
+
+// the hud goes in a band above the board and another below it, each this much of the
+// window's height. small windows have room for them in the padding already
+const HUD_BAND: f32 = 0.08;
+
+const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+const INTEGER_SCALING_KEY: KeyCode = KeyCode::F10;
+
+// used when there's no window to measure, e.g. before the first frame
+const DEFAULT_WINDOW: Vec2 = Vec2::new(500.0, 500.0);
+
+// everything that needs to know how big a tile is on screen reads it from here,
+// instead of each system doing its own math off window.height()
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardLayout {
+    pub tile_size: f32,
+    pub window: Vec2,
+    // the board, in tiles. follows the grid, which changes size for dailies
+    pub columns: i32,
+    pub rows: i32,
+    // how tall each of the hud's bands is, in pixels
+    pub hud_band: f32,
+}
+
+impl Default for BoardLayout {
+    fn default() -> Self {
+        BoardLayout {
+            tile_size: fit_tile_size(DEFAULT_WINDOW, GRID_WIDTH, GRID_HEIGHT, None),
+            window: DEFAULT_WINDOW,
+            columns: GRID_WIDTH,
+            rows: GRID_HEIGHT,
+            hud_band: DEFAULT_WINDOW.y * HUD_BAND,
+        }
+    }
+}
+
+impl BoardLayout {
+    // grid cell -> world position of its center, with the board centered on the camera
+    pub fn cell_center(&self, pos: Position) -> Vec2 {
+        let convert = |pos: i32, bound_game: i32| {
+            (pos as f32 + 0.5 - bound_game as f32 / 2.0) * self.tile_size
+        };
+
+        Vec2::new(convert(pos.x, self.columns), convert(pos.y, self.rows))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LayoutSettings {
+    // only scale the art by whole numbers so pixels stay square
+    pub integer_scaling: bool,
+}
+
+// biggest tile that fits the whole grid (plus padding) into the window, whichever axis is tighter.
+// the hud's bands come off the height, if they need more than the padding.
+// with a texel size, snaps down to a whole multiple of the art's pixels
+fn fit_tile_size(window: Vec2, columns: i32, rows: i32, texel_size: Option<f32>) -> f32 {
+    let tile_size = f32::min(
+        (window.x - PADDING) / columns as f32,
+        (window.y - PADDING.max(2.0 * window.y * HUD_BAND)) / rows as f32,
+    )
+    .max(1.0);
+
+    match texel_size {
+        Some(texel_size) => (tile_size / texel_size).floor().max(1.0) * texel_size,
+        None => tile_size,
+    }
+}
+
+pub(crate) fn update_layout(
+    windows: Option<Res<Windows>>,
+    settings: Res<LayoutSettings>,
+    theme: Res<Theme>,
+    grid: Res<OccupancyGrid>,
+    mut layout: ResMut<BoardLayout>,
+) {
+    // no window (headless, or it's already closed), keep whatever size we had
+    let window = windows
+        .as_ref()
+        .and_then(|windows| windows.get_primary())
+        .map_or(layout.window, |window| Vec2::new(window.width(), window.height()));
+
+    let (columns, rows) = (grid.width(), grid.height());
+    let texel_size = ifelse!(settings.integer_scaling, theme.atlas.tile_size.0);
+
+    let next = BoardLayout {
+        tile_size: fit_tile_size(window, columns, rows, texel_size),
+        window,
+        columns,
+        rows,
+        hud_band: window.y * HUD_BAND,
+    };
+
+    if *layout != next {
+        *layout = next;
+    }
+}
+
+fn layout_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Option<ResMut<Windows>>,
+    mut settings: ResMut<LayoutSettings>,
+) {
+    if keyboard_input.just_pressed(FULLSCREEN_KEY) {
+        if let Some(window) = windows.and_then(|windows| windows.into_inner().get_primary_mut()) {
+            window.set_mode(ifelse!(
+                window.mode() == WindowMode::Windowed,
+                WindowMode::BorderlessFullscreen,
+                WindowMode::Windowed
+            ));
+        }
+    }
+
+    if keyboard_input.just_pressed(INTEGER_SCALING_KEY) {
+        settings.integer_scaling = !settings.integer_scaling;
+    }
+}
+
+// sizes are in tiles, so scaling is just size * tile
+pub(crate) fn size_scaling(layout: Res<BoardLayout>, mut q: Query<(&Size, &mut Transform)>) {
+    for (sprite_size, mut transform) in q.iter_mut() {
+        transform.scale = Vec3::new(
+            sprite_size.width * layout.tile_size,
+            sprite_size.height * layout.tile_size,
+            1.0,
+        );
+    }
+}
+
+// maps the grid to the screen
+pub(crate) fn position_translation(
+    layout: Res<BoardLayout>,
+    settings: Res<LayoutSettings>,
+    mut q: Query<(&Position, &mut Transform)>,
+) {
+    for (pos, mut transform) in q.iter_mut() {
+        let mut translation = layout.cell_center(*pos);
+
+        // odd grids put tile centers on half pixels, which smears pixel art
+        if settings.integer_scaling {
+            translation = translation.round();
+        }
+
+        transform.translation = translation.extend(10.0);
+    }
+}
+
+fn score_text_scaling(layout: Res<BoardLayout>, mut query: Query<&mut Text, With<ScoreText>>) {
+    if !layout.is_changed() {
+        return;
+    }
+
+    for mut text in &mut query {
+        for (i, section) in text.sections.iter_mut().enumerate() {
+            section.style.font_size =
+                ifelse!(i == 0, SCORE_FONT_TILES, COUNTDOWN_FONT_TILES) * layout.tile_size;
+        }
+    }
+}
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BoardLayout::default())
+            .insert_resource(LayoutSettings::default())
+            .add_system(layout_controls)
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::new()
+                    .with_system(update_layout)
+                    .with_system(position_translation.after(update_layout))
+                    .with_system(size_scaling.after(update_layout))
+                    .with_system(score_text_scaling.after(update_layout)),
+            );
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    modes::Rules,
+    notation::parse_board,
+    FoodKind,
+};
+
+const LEVEL_DIR: &str = "assets/levels";
+
+// a board for the levels mode: its size, walls, and whatever moves around on it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub rules: Rules,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level {
+            name: "open field".to_string(),
+            rules: Rules::default(),
+        }
+    }
+}
+
+// every level found on disk, in menu order, and which one is picked
+pub struct Levels {
+    pub list: Vec<Level>,
+    pub current: usize,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels {
+            list: vec![Level::default()],
+            current: 0,
+        }
+    }
+}
+
+impl Levels {
+    pub fn current(&self) -> &Level {
+        &self.list[self.current]
+    }
+}
+
+// a level is either ron, or a .txt board in the text notation named after the file.
+// the levels mode always starts the snake in the same place, so a board's snake is
+// left out, and so is its food besides the mice
+pub fn read_level(path: &Path) -> Result<Level, String> {
+    let s = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let board = match path.extension() {
+        Some(ext) if ext == "txt" => parse_board(&s)?,
+        _ => return ron::from_str(&s).map_err(|e| e.to_string()),
+    };
+
+    Ok(Level {
+        name: path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace('_', " "))
+            .unwrap_or_default(),
+        rules: Rules {
+            creatures: board.creatures.into_iter().map(|(_, creature)| creature).collect(),
+            mice: board
+                .food
+                .iter()
+                .filter(|(_, kind)| *kind == FoodKind::Mouse)
+                .map(|(pos, _)| *pos)
+                .collect(),
+            ..board.rules
+        },
+    })
+}
+
+// alphabetical by file name. broken files are skipped with a warning, same as themes
+fn load_levels() -> Levels {
+    let dir = FileAssetIo::get_base_path().join(LEVEL_DIR);
+
+    let mut paths = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "ron" || ext == "txt"))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+
+    paths.sort();
+
+    let list = paths
+        .iter()
+        .filter_map(|path| {
+            match read_level(path) {
+                Ok(level) => Some(level),
+                Err(e) => {
+                    warn!("skipping level {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect::<Vec<Level>>();
+
+    ifelse!(list.is_empty(), Levels::default(), Levels { list, current: 0 })
+}
+
+fn levels_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut levels: ResMut<Levels>,
+    mut config: ResMut<Config>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        let step = match event {
+            MenuEvent::Adjust("level", step) => *step,
+            MenuEvent::Activate("level") => 1,
+            _ => continue,
+        };
+
+        let count = levels.list.len() as i32;
+        levels.current = (levels.current as i32 + step).rem_euclid(count) as usize;
+        config.level = levels.current().name.clone();
+    }
+
+    if menu.open {
+        entries.set(
+            MenuPage::Main,
+            "level",
+            25,
+            locale.format("menu.level", &[("name", levels.current().name.clone())]),
+        );
+    }
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own
+        if !app.world.contains_resource::<Levels>() {
+            let mut levels = load_levels();
+
+            if let Some(config) = app.world.get_resource::<Config>() {
+                if let Some(i) = levels.list.iter().position(|l| l.name == config.level) {
+                    levels.current = i;
+                }
+            }
+
+            app.insert_resource(levels);
+        }
+
+        app.add_system(levels_menu);
+    }
+}
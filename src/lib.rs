@@ -1,3 +1,7 @@
+// everything that makes up the game, as plugins. the binary's just DefaultPlugins
+// and these, so other crates can build on the same pieces
+// bevy systems take lots of params and big query types, that's just how it is
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
 #![macro_use]
 
 #[macro_export]
@@ -25,4 +29,1114 @@ macro_rules! log {
     ($i: ident) => {
         println!("{}: {:#?}", stringify!($i), $i)
     };
-}
\ No newline at end of file
+}
+
+use bevy::{app::PluginGroupBuilder, prelude::*, render::texture::*};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
+
+pub mod occupancy;
+pub mod rng;
+
+use occupancy::{Cell, OccupancyGrid};
+use rng::GameRng;
+
+mod accessibility;
+mod audio;
+mod config;
+mod creatures;
+mod daily;
+mod effects;
+mod hud;
+mod layout;
+mod levels;
+mod locale;
+mod menu;
+mod modes;
+mod notation;
+mod portals;
+mod puzzles;
+mod raster;
+mod recording;
+mod rewind;
+mod save;
+mod screenshot;
+mod scripting;
+mod stats;
+mod theme;
+mod tick;
+
+#[cfg(test)]
+mod tests;
+
+pub use accessibility::AccessibilityPlugin;
+pub use audio::SoundPlugin;
+pub use config::{Config, ConfigPlugin};
+pub use creatures::{Creature, CreaturePlugin};
+pub use daily::DailyPlugin;
+pub use effects::EffectsPlugin;
+pub use hud::{HudPlugin, PowerUp, PowerUps};
+pub use layout::LayoutPlugin;
+pub use levels::LevelPlugin;
+pub use locale::LocalePlugin;
+pub use menu::MenuPlugin;
+pub use modes::{GameMode, ModePlugin, Round, Rules};
+pub use notation::{parse_board, print_board};
+pub use portals::PortalPlugin;
+pub use puzzles::{parse_puzzle, solve, PuzzlePlugin};
+pub use raster::Frame;
+pub use recording::{
+    export_replay, parse_args, RecordArgs, RecordFormat, RecordPlugin, RecordSettings, USAGE,
+};
+pub use rewind::RewindPlugin;
+pub use save::SavePlugin;
+pub use screenshot::ScreenshotPlugin;
+pub use scripting::ScriptPlugin;
+pub use stats::StatsPlugin;
+pub use theme::ThemePlugin;
+pub use tick::{Tick, TickClock, TickPlugin, TickStage, TickStageLabel};
+
+use accessibility::MIN_GAME_SPEED;
+use effects::MainCamera;
+use layout::BoardLayout;
+use locale::Locale;
+use menu::Menu;
+use modes::{HighScores, OutOfMovesEvent, TimeUpEvent};
+use scripting::{BoardView, Scripts};
+use theme::{insert_food_sprite, SnakePart, Theme};
+
+// in tiles; a hair over 1 so the segments overlap and corners join up
+const SNAKE_SIZE: f32 = 1.016;
+const GRID_WIDTH: i32 = 20;
+const GRID_HEIGHT: i32 = 20;
+const PADDING: f32 = 100.0;
+const SNAKE_STEP: f64 = 125.0;
+
+/// labels on the game's own systems, to put yours before or after.
+/// Input runs every frame in CoreStage::Update. Move, Collide and Score run once a tick
+/// in the tick stage, in that order. Render is the sprite updating, in Update and PostUpdate
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameSystem {
+    Input,
+    Move,
+    Collide,
+    Score,
+    Render,
+}
+
+/// the front of the snake. also a SnakeSegment
+#[derive(Component)]
+pub struct SnakeHead;
+
+#[derive(Default)]
+struct LastTailPosition(Option<Position>);
+
+#[derive(Default, Debug, Copy, Clone)]
+struct LastTailDirection(Option<DirectionPair>);
+
+/// points so far this game
+#[derive(Default)]
+pub struct Score(pub u128);
+
+/// every piece of the snake, head included
+#[derive(Component)]
+pub struct SnakeSegment;
+
+/// the snake's entities in order, head at the front, tail at the back
+#[derive(Default, Deref, DerefMut, Debug, Clone)]
+pub struct SnakeSegments(VecDeque<Entity>);
+
+impl SnakeSegments {
+    // which bit of the snake the segment at index i is, for picking sprites
+    fn part(&self, i: usize) -> SnakePart {
+        if i == 0 {
+            SnakePart::Head
+        } else if i == self.len() - 1 {
+            SnakePart::Tail
+        } else {
+            SnakePart::Body
+        }
+    }
+}
+
+#[derive(Component)]
+struct ScoreText;
+
+/// some assembly required. Null is standing still, before the first key press
+#[derive(Component, Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SnakeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+    Null,
+}
+
+// proud of this type; pair.0 is the previous / entry direction,
+// pair.1 is the inputted / exit direction
+
+/*
+dirpair:       snake:
+
+input: left
+_________
+|       |       O o o <
+< l     |           o
+|___^u__|           o
+ prev: up
+
+*/
+
+/// which way a segment came in (.0) and which way it goes out (.1)
+#[derive(Component, Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DirectionPair(pub SnakeDirection, pub SnakeDirection);
+
+/// a cell on the board. bring-your-own-grid day, 0, 0 is the bottom left
+#[derive(Component, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    /// the cell next door in that direction. Null stays put
+    pub fn step(self, dir: SnakeDirection) -> Position {
+        use SnakeDirection::*;
+
+        match dir {
+            Left => Position { x: self.x - 1, y: self.y },
+            Right => Position { x: self.x + 1, y: self.y },
+            Up => Position { x: self.x, y: self.y + 1 },
+            Down => Position { x: self.x, y: self.y - 1 },
+            Null => self,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Size {
+    width: f32,
+    height: f32,
+}
+
+impl Size {
+    fn square(x: f32) -> Self {
+        Self {
+            width: x,
+            height: x,
+        }
+    }
+}
+
+/// anything the snake can eat. goes with a FoodKind, a Position and a Food cell on the grid
+#[derive(Component)]
+pub struct Food;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FoodKind {
+    Normal,
+    // rarer, worth more, and just as filling
+    Bonus,
+    // runs away from the head. comes back as another mouse once it's caught
+    Mouse,
+    /// food from another crate. the id's yours to tell them apart by,
+    /// it's drawn like normal food unless you draw it yourself
+    Custom { id: u32, points: u32 },
+}
+
+impl FoodKind {
+    pub fn points(self) -> u128 {
+        match self {
+            FoodKind::Normal => 1,
+            FoodKind::Bonus => 3,
+            FoodKind::Mouse => 2,
+            FoodKind::Custom { points, .. } => points as u128,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Outline;
+
+// a wall in the middle of the board, drawn like the outline
+#[derive(Component)]
+struct Obstacle;
+
+// built from the current theme's atlas by the theme plugin
+#[derive(Default, Debug, Clone)]
+struct SpriteSheet(Handle<TextureAtlas>);
+
+// grows the snake back into the spot its tail just left, when it ate
+// or when survival mode says it's time
+fn scored(
+    mut commands: Commands,
+    mut segments: ResMut<SnakeSegments>,
+    mut score_reader: EventReader<ScoredEvent>,
+    last_tail_position: Res<LastTailPosition>,
+    last_tail_direction: Res<LastTailDirection>,
+    mut score: ResMut<Score>,
+    mut grid: ResMut<OccupancyGrid>,
+    round: Res<Round>,
+    sprite_sheet: Res<SpriteSheet>,
+    theme: Res<Theme>,
+) {
+    let points = score_reader
+        .iter()
+        .map(|ScoredEvent(_, kind)| kind.points())
+        .sum::<u128>();
+    let ate = points > 0;
+
+    // survival scores by the second instead
+    if round.mode != GameMode::Survival {
+        score.0 += points;
+    }
+
+    // no tail position means the snake didn't move this tick, so there's nowhere to grow into
+    if let (true, Some(pos), Some(dir)) = (
+        ate || round.grow_due(),
+        last_tail_position.0,
+        last_tail_direction.0,
+    ) {
+        grid.set(pos.x, pos.y, Cell::Snake);
+        segments.push_back(spawn_segment(&mut commands, pos, dir, &sprite_sheet.0, &theme));
+    }
+}
+
+fn spawn_food(
+    mut commands: Commands,
+    mut score_reader: EventReader<ScoredEvent>,
+    mut rival_reader: EventReader<RivalAteEvent>,
+    food: Query<(), With<Food>>,
+    mut full_writer: EventWriter<BoardFullEvent>,
+    mut grid: ResMut<OccupancyGrid>,
+    mut rng: ResMut<GameRng>,
+    round: Res<Round>,
+    sprite_sheet: Res<SpriteSheet>,
+    theme: Res<Theme>,
+    mut scripts: ResMut<Scripts>,
+    board: BoardView,
+    score: Res<Score>,
+) {
+    let eaten = score_reader
+        .iter()
+        .map(|ScoredEvent(_, kind)| Some(*kind))
+        .chain(rival_reader.iter().map(|RivalAteEvent(kind)| Some(*kind)))
+        .collect::<Vec<_>>();
+
+    // a puzzle's food comes in order, one at a time, and it's all plain so the score
+    // is how much has gone. it waits for the snake to get off its cell, and once
+    // there's none left that's the puzzle done. what was eaten this tick is still
+    // in the query until the commands go through
+    if !round.rules.food.is_empty() {
+        if food.iter().count() > eaten.len() {
+            return;
+        }
+
+        match round.rules.food.get(score.0 as usize) {
+            Some(pos) if grid.get(pos.x, pos.y) == Cell::Empty => {
+                grid.set(pos.x, pos.y, Cell::Food);
+                spawn_food_piece(&mut commands, *pos, FoodKind::Normal, &sprite_sheet, &theme);
+            }
+            Some(_) => (),
+            None => full_writer.send(BoardFullEvent),
+        }
+
+        return;
+    }
+
+    // the first tick puts the first food down, after that it's one for one,
+    // whoever did the eating
+    let wanted = ifelse!(eaten.is_empty() && food.is_empty(), vec![None], eaten);
+
+    for eaten in wanted {
+        // a mod gets first pick. nowhere left to put it means the snake's filled the board
+        let pos = match scripts.food_position(&grid, || board.map(&grid)) {
+            Some(pos) => pos,
+            None => match grid.sample_free(&mut *rng) {
+                Some((x, y)) => Position { x, y },
+                None => {
+                    full_writer.send(BoardFullEvent);
+                    return;
+                }
+            },
+        };
+
+        grid.set(pos.x, pos.y, Cell::Food);
+
+        let kind = match eaten {
+            Some(FoodKind::Mouse) => FoodKind::Mouse,
+            _ => ifelse!(
+                rng.gen_bool(round.rules.bonus_chance),
+                FoodKind::Bonus,
+                FoodKind::Normal
+            ),
+        };
+
+        spawn_food_piece(&mut commands, pos, kind, &sprite_sheet, &theme);
+    }
+}
+
+// just the entity, the grid's up to the caller
+fn spawn_food_piece(
+    commands: &mut Commands,
+    pos: Position,
+    kind: FoodKind,
+    sprite_sheet: &SpriteSheet,
+    theme: &Theme,
+) -> Entity {
+    let mut food = commands.spawn_bundle(SpriteBundle::default());
+
+    insert_food_sprite(&mut food, kind, theme, sprite_sheet);
+
+    food.insert(pos).insert(Size::square(1.0)).insert(Food).insert(kind).id()
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands
+        .spawn_bundle(Camera2dBundle { ..default() })
+        .insert(MainCamera);
+}
+
+// a fresh three long snake near the bottom left, not going anywhere yet,
+// unless the board says where it starts
+fn spawn_snake(
+    commands: &mut Commands,
+    grid: &mut OccupancyGrid,
+    start: &[(Position, DirectionPair)],
+    sprite_sheet: &SpriteSheet,
+    theme: &Theme,
+) -> SnakeSegments {
+    let still = DirectionPair(SnakeDirection::Null, SnakeDirection::Null);
+    let usual = [
+        (Position { x: 3, y: 3 }, still),
+        (Position { x: 3, y: 2 }, still),
+        (Position { x: 3, y: 1 }, still),
+    ];
+
+    let start = ifelse!(start.is_empty(), &usual[..], start);
+
+    SnakeSegments(
+        start
+            .iter()
+            .enumerate()
+            .map(|(i, (pos, dir))| {
+                grid.set(pos.x, pos.y, Cell::Snake);
+
+                let spawn = ifelse!(i == 0, spawn_head, spawn_segment);
+                spawn(commands, *pos, *dir, &sprite_sheet.0, theme)
+            })
+            .collect(),
+    )
+}
+
+fn snake_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    menu: Res<Menu>,
+    mut head_directions: Query<&mut DirectionPair, With<SnakeHead>>,
+    prev_directions: Query<&DirectionPair, Without<SnakeHead>>,
+) {
+    use SnakeDirection::*;
+
+    // the arrows belong to the menu while it's up
+    if menu.open {
+        return;
+    }
+
+    for (mut direction, prev_directions) in
+        //  |   3 damn days for like 5 lines
+        //  V   it made corners work but ugh
+        head_directions.iter_mut().zip(prev_directions.iter())
+    {
+        // what's all this mess? that's right, it's a bad fix for a weird problem.
+        // on seemingly random occasions dirpairs would replicate themselves
+        // until the line was all corner sprites
+
+        // the fix is to use some logic to check "hey, is this corner gonna look weird?"
+        // it would be nicer to actually fix the replication but it's a very bizarre bug
+        // nothing more permanent than a temporary solution
+
+        if (keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A))
+            && direction.1 != Right
+        {
+            // if it looks bad, assume it's a straight line
+            if direction.0 != direction.1 && *direction == *prev_directions {
+                direction.0 = Left;
+                direction.1 = Left;
+            } else {
+                // if it looks good, promote old dir.1
+                direction.0 = prev_directions.1;
+                direction.1 = Left;
+            }
+        } else if (keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D))
+            && direction.1 != Left
+        {
+            if direction.0 != direction.1 && *direction == *prev_directions {
+                direction.0 = Right;
+                direction.1 = Right;
+            } else {
+                direction.0 = prev_directions.1;
+                direction.1 = Right;
+            }
+        } else if (keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S))
+            && direction.1 != Up
+        {
+            if direction.0 != direction.1 && *direction == *prev_directions {
+                direction.0 = Down;
+                direction.1 = Down;
+            } else {
+                direction.0 = prev_directions.1;
+                direction.1 = Down;
+            }
+        } else if (keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W))
+            && direction.1 != Down
+        {
+            if direction.0 != direction.1 && *direction == *prev_directions {
+                direction.0 = Up;
+                direction.1 = Up;
+            } else {
+                direction.0 = prev_directions.1;
+                direction.1 = Up;
+            }
+        }
+    }
+}
+
+// moves the snake one cell. instead of shuffling every segment down the line,
+// the tail jumps into the gap the head just left and becomes the neck,
+// so a step costs the same no matter how long the snake is
+fn snake_movement(
+    mut commands: Commands,
+    mut segments: ResMut<SnakeSegments>,
+    mut positions: Query<&mut Position>,
+    mut directions: Query<&mut DirectionPair>,
+    mut last_tail_position: ResMut<LastTailPosition>,
+    mut last_tail_direction: ResMut<LastTailDirection>,
+    mut grid: ResMut<OccupancyGrid>,
+    round: Res<Round>,
+    mut death_writer: EventWriter<DeathEvent>,
+    mut collision_writer: EventWriter<CollisionEvent>,
+) {
+    // only set again if the snake actually moves
+    *last_tail_position = LastTailPosition(None);
+    *last_tail_direction = LastTailDirection(None);
+
+    let (head, tail) = match (segments.front(), segments.back()) {
+        (Some(head), Some(tail)) => (*head, *tail),
+        _ => return,
+    };
+
+    let head_pos = *positions.get(head).unwrap();
+    let head_dir = *directions.get(head).unwrap();
+    let tail_pos = *positions.get(tail).unwrap();
+    let tail_dir = *directions.get(tail).unwrap();
+
+    if head_dir.1 == SnakeDirection::Null {
+        return;
+    }
+
+    let future_pos = round.rules.step(head_pos, head_dir.1);
+
+    // walls, the board edge and the snake itself (tail included) all block.
+    // bumping into something costs the tail instead, unless the mode says otherwise
+    if !matches!(grid.get(future_pos.x, future_pos.y), Cell::Empty | Cell::Food) {
+        collision_writer.send(CollisionEvent(future_pos, grid.get(future_pos.x, future_pos.y)));
+
+        if round.mode.bumps_kill() {
+            death_writer.send(DeathEvent);
+            return;
+        }
+
+        // zen never takes the head
+        if segments.len() == 1 && !round.mode.can_die() {
+            return;
+        }
+
+        grid.set(tail_pos.x, tail_pos.y, Cell::Empty);
+
+        commands.entity(tail).despawn();
+        segments.pop_back();
+
+        // that was the head, nothing left to shrink
+        if segments.is_empty() {
+            death_writer.send(DeathEvent);
+        }
+        return;
+    }
+
+    grid.set(future_pos.x, future_pos.y, Cell::Snake);
+    grid.set(tail_pos.x, tail_pos.y, Cell::Empty);
+    *positions.get_mut(head).unwrap() = future_pos;
+
+    // a lone head has no tail to move up behind it
+    if segments.len() > 1 {
+        segments.pop_back();
+        segments.insert(1, tail);
+
+        *positions.get_mut(tail).unwrap() = head_pos;
+        *directions.get_mut(tail).unwrap() = head_dir;
+    }
+
+    // scored() grows the snake back into this spot if we just ate
+    *last_tail_position = LastTailPosition(Some(tail_pos));
+    *last_tail_direction = LastTailDirection(Some(tail_dir));
+}
+
+/// the snake ate: where, and what it was
+pub struct ScoredEvent(pub Position, pub FoodKind);
+
+/// a rival snake got there first, and what it ate
+pub struct RivalAteEvent(pub FoodKind);
+
+/// the snake's out of lives, the game ends this tick
+pub struct DeathEvent;
+
+/// the head ran into something, and what it was. sent whether or not it was fatal
+pub struct CollisionEvent(pub Position, pub Cell);
+
+impl CollisionEvent {
+    /// what got hit, by name: "wall", "snake", "portal", or "edge" for off the board
+    pub fn cause(&self, grid: &OccupancyGrid) -> &'static str {
+        ifelse!(grid.in_bounds(self.0.x, self.0.y), self.1.name(), "edge")
+    }
+}
+
+/// there's nowhere left to put food, i.e. the snake filled the board
+pub struct BoardFullEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Died,
+    Won,
+    TimeUp,
+    OutOfMoves,
+}
+
+/// how the game ended, if it has
+#[derive(Default)]
+pub struct GameOver(pub Option<Outcome>);
+
+#[derive(Component)]
+struct Banner;
+
+// banner text is sized in tiles, like everything else
+const BANNER_FONT_TILES: f32 = 2.0;
+
+fn setup_banner(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::from_section("", style).with_alignment(TextAlignment::CENTER),
+            transform: Transform::from_xyz(0.0, 0.0, 50.0),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(Banner);
+}
+
+fn end_game(
+    mut game_over: ResMut<GameOver>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut full_reader: EventReader<BoardFullEvent>,
+    mut time_up_reader: EventReader<TimeUpEvent>,
+    mut out_of_moves_reader: EventReader<OutOfMovesEvent>,
+    round: Res<Round>,
+    high_scores: Res<HighScores>,
+    layout: Res<BoardLayout>,
+    mut banner: Query<(&mut Text, &mut Visibility), With<Banner>>,
+    locale: Res<Locale>,
+) {
+    if time_up_reader.iter().next().is_some() {
+        game_over.0 = Some(Outcome::TimeUp);
+    }
+
+    if out_of_moves_reader.iter().next().is_some() {
+        game_over.0 = Some(Outcome::OutOfMoves);
+    }
+
+    if death_reader.iter().next().is_some() {
+        game_over.0 = Some(Outcome::Died);
+    }
+
+    // filling the board beats dying on the same tick, and so does eating
+    // a puzzle's last food on its last move
+    if full_reader.iter().next().is_some() {
+        game_over.0 = Some(Outcome::Won);
+    }
+
+    if !game_over.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in &mut banner {
+        visibility.is_visible = game_over.0.is_some();
+        text.sections[0].style.font_size = BANNER_FONT_TILES * layout.tile_size;
+        let headline = match game_over.0 {
+            Some(Outcome::Won) if round.mode == GameMode::Puzzle => "banner.solved",
+            Some(Outcome::Won) => "banner.won",
+            Some(Outcome::Died) => "banner.died",
+            Some(Outcome::TimeUp) => "banner.time_up",
+            Some(Outcome::OutOfMoves) => "banner.out_of_moves",
+            None => "",
+        };
+
+        text.sections[0].value = [
+            locale.get(headline),
+            locale.format("banner.best", &[("best", high_scores.best(&round.score_key()).to_string())]),
+            locale.get("banner.new_game"),
+            locale.get("banner.recording"),
+        ]
+        .join("\n");
+    }
+}
+
+// sometimes caveman solution is the solution
+fn collision_detection(
+    mut commands: Commands,
+    mut score_writer: EventWriter<ScoredEvent>,
+    mut rival_writer: EventWriter<RivalAteEvent>,
+    snake_head: Query<(Entity, &Position), With<SnakeHead>>,
+    food: Query<(Entity, &Position, &FoodKind), With<Food>>,
+    segments: Query<&Position, (With<SnakeSegment>, Without<SnakeHead>)>,
+    mut creatures: Query<&mut Creature>,
+) {
+    for snake_pos in snake_head.iter() {
+        for (ent, food_pos, kind) in food.iter() {
+            if snake_pos.1 == food_pos {
+                commands.entity(ent).despawn();
+                score_writer.send(ScoredEvent(*food_pos, *kind));
+            }
+        }
+
+        for segment_pos in segments.iter() {
+            if snake_pos.1 == segment_pos {
+                //despawn_writer.send(DespawnEvent);
+            }
+        }
+    }
+
+    // rivals eat too. the grid never lets two heads onto the same food
+    for mut creature in creatures.iter_mut() {
+        if let Creature::Rival { body, grow } = &mut *creature {
+            for (ent, food_pos, kind) in food.iter() {
+                if body.front() == Some(food_pos) {
+                    commands.entity(ent).despawn();
+                    *grow += 1;
+                    rival_writer.send(RivalAteEvent(*kind));
+                }
+            }
+        }
+    }
+}
+
+/*
+fn setup_board(mut commands: Commands) {
+    for x in 0..(GRID_WIDTH) {
+        if x % 2 == 0 {
+            for y in (0..(GRID_HEIGHT - 1)).step_by(2) {
+                draw_bg_element(x, y, 1.0, 1.0, (0.027, 0.212, 0.259), &mut commands)
+            }
+        } else {
+            for y in (1..(GRID_HEIGHT)).step_by(2) {
+                draw_bg_element(x, y, 1.0, 1.0, (0.027, 0.212, 0.259), &mut commands)
+            }
+        }
+    }
+}
+*/
+
+// draw the outline using math!!!!!
+// todo: change to sprites instead of transform shapes.
+// redrawn whenever a new game changes the board size
+fn draw_outline(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    grid: Res<OccupancyGrid>,
+    outline: Query<Entity, (With<Outline>, Without<Obstacle>)>,
+    mut drawn: Local<Option<(i32, i32)>>,
+) {
+    let (width, height) = (grid.width(), grid.height());
+
+    if *drawn == Some((width, height)) {
+        return;
+    }
+
+    for ent in outline.iter() {
+        commands.entity(ent).despawn();
+    }
+
+    let color = theme.palette.outline;
+
+    for y in 0..height {
+        draw_bg_element(-1, y, 1.0, 0.5, color, &mut commands);
+        draw_bg_element(width, y, 1.0, 0.5, color, &mut commands);
+    }
+
+    for x in 0..width {
+        draw_bg_element(x, -1, 0.5, 1.0, color, &mut commands);
+        draw_bg_element(x, height, 0.5, 1.0, color, &mut commands);
+    }
+
+    *drawn = Some((width, height));
+}
+
+// back in my day we had to draw the border uphill both ways
+fn draw_bg_element(
+    x: i32,
+    y: i32,
+    h: f32,
+    w: f32,
+    color: Color,
+    commands: &mut Commands,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            ..default()
+        })
+        .insert(Outline)
+        .insert(Size {
+            width: w,
+            height: h,
+        })
+        .insert(Position { x, y })
+        .id()
+}
+
+// spawn_segment, but with a face
+fn spawn_head(
+    commands: &mut Commands,
+    pos: Position,
+    dir: DirectionPair,
+    texture_atlas_handle: &Handle<TextureAtlas>,
+    theme: &Theme,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                index: theme.tiles.head.get(dir.1),
+                color: theme.palette.head_tint,
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            },
+            texture_atlas: texture_atlas_handle.clone(),
+            ..default()
+        })
+        .insert(Size::square(SNAKE_SIZE))
+        .insert(SnakeHead)
+        .insert(SnakeSegment)
+        .insert(pos)
+        .insert(dir)
+        .id()
+}
+
+// copied but shrunken spawn_snake, since it doesn't need to init anything
+fn spawn_segment(
+    commands: &mut Commands,
+    pos: Position,
+    dir: DirectionPair,
+    texture_atlas_handle: &Handle<TextureAtlas>,
+    theme: &Theme,
+) -> Entity {
+    let index = theme.tiles.segment(dir);
+    let color = theme.palette.segment_tint;
+
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                index,
+                color,
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            },
+            texture_atlas: texture_atlas_handle.clone(),
+            ..default()
+        })
+        .insert(Size::square(SNAKE_SIZE))
+        .insert(SnakeSegment)
+        .insert(dir)
+        .insert(pos)
+        .id()
+}
+
+// assigns indexes to dirpairs,
+// changes sprite textures based on the type of dirpair (corner) detected.
+// the theme decides which tile is which.
+// only the ends of the snake ever change: the neck that just moved in behind the head,
+// and the tail plus whatever's next to it after growing or shrinking.
+// a segment going into a portal keeps its dirpair, so it's drawn straight (or
+// cornered) into the portal's mouth and the next one comes out the other side
+fn update_textures(
+    theme: Res<Theme>,
+    segments: Res<SnakeSegments>,
+    mut query: Query<(&mut TextureAtlasSprite, &DirectionPair), Without<SnakeHead>>,
+) {
+    let len = segments.len();
+
+    // the head's done every frame by animate_head
+    for i in [1, len.saturating_sub(2), len.saturating_sub(1)] {
+        if i == 0 || i >= len {
+            continue;
+        }
+
+        if let Ok((mut sprite, snake_direction)) = query.get_mut(segments[i]) {
+            sprite.index = theme.tiles.part(segments.part(i), *snake_direction);
+        }
+    }
+}
+
+// how long the mouth stays open after eating
+const CHOMP_TIME: f32 = 0.2;
+
+#[derive(Component)]
+struct Chomping(Timer);
+
+// the head faces where it's going, and opens wide when there's food
+// right in front of it or it's just eaten
+fn animate_head(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    mut score_reader: EventReader<ScoredEvent>,
+    mut heads: Query<
+        (Entity, &mut TextureAtlasSprite, &Position, &DirectionPair, Option<&mut Chomping>),
+        With<SnakeHead>,
+    >,
+    food: Query<&Position, With<Food>>,
+    round: Res<Round>,
+) {
+    let scored = score_reader.iter().next().is_some();
+
+    for (head, mut sprite, pos, dir, chomping) in heads.iter_mut() {
+        let chomping = match chomping {
+            Some(mut chomping) => !chomping.0.tick(time.delta()).finished(),
+            None => false,
+        };
+
+        if scored {
+            commands
+                .entity(head)
+                .insert(Chomping(Timer::from_seconds(CHOMP_TIME, false)));
+        }
+
+        // through a portal, if that's where it's headed
+        let ahead = round.rules.step(*pos, dir.1);
+        let food_ahead = dir.1 != SnakeDirection::Null && food.iter().any(|food| *food == ahead);
+
+        sprite.index = ifelse!(
+            scored || chomping || food_ahead,
+            theme.tiles.eating.get(dir.1),
+            theme.tiles.head.get(dir.1)
+        );
+    }
+}
+
+fn setup_score_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+) {
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        // resized to fit the board by the layout plugin
+        font_size: 250.0,
+        color: theme.palette.score_text,
+    };
+
+    // the score, then the time attack countdown under it
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::from_sections([
+                TextSection::new("0", style.clone()),
+                TextSection::new("", style),
+            ])
+            .with_alignment(TextAlignment::CENTER),
+            transform: Transform::from_scale(Vec3::splat(1.0)),
+            ..default()
+        })
+        .insert(ScoreText);
+}
+
+fn update_score_text(
+    mut query: Query<&mut Text, With<ScoreText>>,
+    score: Res<Score>,
+    round: Res<Round>,
+    locale: Res<Locale>,
+) {
+    for mut text in &mut query {
+        text.sections[0].value = match score.0.to_string().chars().count() {
+            1 => format!("00{}", score.0),
+            2 => format!("0{}", score.0),
+            _ => format!("{}", score.0),
+        };
+
+        // rounded up, so it says 0:00 exactly when time's up
+        text.sections[1].value = match (round.time_left(), round.moves_left()) {
+            (Some(left), _) => {
+                let secs = left.as_secs() + ifelse!(left.subsec_nanos() > 0, 1, 0);
+                format!("\n{}:{:02}", secs / 60, secs % 60)
+            }
+            (None, Some(moves)) => {
+                format!("\n{}", locale.format("hud.moves", &[("moves", moves.to_string())]))
+            }
+            (None, None) => String::new(),
+        };
+    }
+}
+
+// faster the more you've eaten, slower out near the edges
+fn update_speed(
+    mut clock: ResMut<TickClock>,
+    score: Res<Score>,
+    round: Res<Round>,
+    grid: Res<OccupancyGrid>,
+    pos: Query<&Position>,
+    head: Query<Entity, With<SnakeHead>>,
+    mut scripts: ResMut<Scripts>,
+    config: Res<Config>,
+) {
+    let mut head_pos = Position { x: 0, y: 0 };
+
+    for i in head.iter() {
+        head_pos = *pos.get(i).unwrap();
+    }
+
+    let (x, y) = (head_pos.x, head_pos.y);
+
+    let mut x_factor = (((grid.width() - x) + (0 - x)) / 2).abs();
+    if x_factor < 8 { x_factor = 0 };
+    let mut y_factor = (((grid.height() - y) + (0 - y)) / 2).abs();
+    if y_factor < 8 { y_factor = 0 };
+
+    let factor = std::cmp::max(x_factor, y_factor);
+
+    // survival's score is the clock, it'd get silly fast
+    let eaten = ifelse!(round.mode == GameMode::Survival, 0, score.0);
+
+    clock.step = Duration::from_millis(
+        (SNAKE_STEP + (factor as f64 * 2.0) - (eaten as f64 * 2.0)) as u64,
+    );
+
+    // a mod can take over the speed entirely
+    if let Some(step) = scripts.speed_for(score.0) {
+        clock.step = step;
+    }
+
+    // the player's speed setting goes on top of everything, mods included
+    let percent = config.game_speed.clamp(MIN_GAME_SPEED, 100);
+    clock.step = clock.step * 100 / percent;
+}
+
+/// the window, camera, and the resources and events everything else leans on
+pub struct SetupPlugin;
+
+impl Plugin for SetupPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WindowDescriptor {
+            title: "snek".to_string(),
+            width: 500.0,
+            height: 500.0,
+            ..default()
+        })
+        .insert_resource(ImageSettings::default_nearest())
+        .insert_resource(SpriteSheet::default())
+        .add_startup_system(setup_score_text)
+        .add_event::<ScoredEvent>()
+        .add_event::<RivalAteEvent>()
+        .add_event::<DeathEvent>()
+        .add_event::<CollisionEvent>()
+        .add_event::<BoardFullEvent>()
+        .add_startup_system(setup_banner)
+        .insert_resource(GameOver::default())
+        .insert_resource(OccupancyGrid::new(GRID_WIDTH, GRID_HEIGHT))
+        .add_startup_system(setup_camera)
+        .insert_resource(SnakeSegments::default())
+        .insert_resource(LastTailPosition::default())
+        .insert_resource(LastTailDirection::default())
+        .insert_resource(Score(0));
+    }
+}
+
+/// the rules: steering, moving, eating, growing and dying
+pub struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        // input's read every frame so short taps aren't missed, everything that
+        // moves the game forward runs once per tick in the tick stage
+        app.add_system(snake_controls.label(GameSystem::Input))
+            .stage(TickStageLabel, |stage: &mut TickStage| {
+                stage.add_system_set(
+                    SystemSet::new()
+                        .with_system(snake_movement.label(GameSystem::Move))
+                        .with_system(
+                            collision_detection
+                                .label(GameSystem::Collide)
+                                .after(GameSystem::Move),
+                        )
+                        .with_system(scored.label(GameSystem::Score).after(GameSystem::Collide))
+                        .with_system(spawn_food.after(GameSystem::Score))
+                        .with_system(end_game.after(spawn_food))
+                        .with_system(update_speed.after(GameSystem::Score)),
+                )
+            });
+    }
+}
+
+/// keeps the snake's sprites, the score and the board edge drawn.
+/// leave it out to draw things your own way
+pub struct GameRenderPlugin;
+
+impl Plugin for GameRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_score_text.label(GameSystem::Render))
+            .add_system(animate_head.label(GameSystem::Render))
+            .add_system(draw_outline.label(GameSystem::Render).after(modes::new_game))
+            // every frame rather than every tick, so a tail spawned by scored()
+            // gets its sprite as soon as it exists
+            .add_system_to_stage(CoreStage::PostUpdate, update_textures.label(GameSystem::Render));
+    }
+}
+
+/// the whole game, minus DefaultPlugins and the sound, which go after it:
+///
+/// app.add_plugins(SnekPlugins).add_plugins(DefaultPlugins).add_plugin(SoundPlugin)
+///
+/// the order matters, the later plugins build on what the earlier ones set up
+pub struct SnekPlugins;
+
+impl PluginGroup for SnekPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(SetupPlugin)
+            .add(ConfigPlugin)
+            .add(LocalePlugin)
+            .add(ThemePlugin)
+            .add(MenuPlugin)
+            .add(LayoutPlugin)
+            .add(AccessibilityPlugin)
+            .add(EffectsPlugin)
+            .add(TickPlugin)
+            .add(GameplayPlugin)
+            .add(GameRenderPlugin)
+            .add(HudPlugin)
+            .add(LevelPlugin)
+            .add(ModePlugin)
+            .add(PuzzlePlugin)
+            .add(CreaturePlugin)
+            .add(PortalPlugin)
+            .add(ScriptPlugin)
+            .add(DailyPlugin)
+            .add(RewindPlugin)
+            .add(SavePlugin)
+            .add(StatsPlugin)
+            .add(RecordPlugin)
+            .add(ScreenshotPlugin);
+    }
+}
@@ -0,0 +1,266 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use crate::{
+    config::Config,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+};
+
+// compiled in so there's always something to say, even with no lang folder
+const ENGLISH: &str = include_str!("../assets/lang/en.txt");
+const LANG_DIR: &str = "assets/lang";
+
+// has latin, greek and cyrillic, but nothing for chinese, japanese or korean
+pub const DEFAULT_FONT: &str = "FiraMono-Regular.ttf";
+
+// one language file: its strings by key, and the fonts that can draw them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Language {
+    // the file name, e.g. en
+    pub code: String,
+    pub name: String,
+    // in order of preference
+    pub fonts: Vec<String>,
+    strings: HashMap<String, String>,
+}
+
+// `key = value` a line, # for comments. language.name and language.font are about the
+// file itself rather than something to show
+pub fn parse_language(code: &str, text: &str) -> Result<Language, String> {
+    let mut strings = HashMap::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: should be key = value", n + 1))?;
+
+        strings.insert(key.trim().to_string(), value.trim().to_string());
+    }
+
+    Ok(Language {
+        code: code.to_string(),
+        name: strings.remove("language.name").unwrap_or_else(|| code.to_string()),
+        fonts: strings
+            .remove("language.font")
+            .map(|fonts| fonts.split(',').map(|font| font.trim().to_string()).collect())
+            .unwrap_or_default(),
+        strings,
+    })
+}
+
+impl Language {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.strings.keys()
+    }
+}
+
+fn font_exists(font: &str) -> bool {
+    FileAssetIo::get_base_path().join("assets").join(font).exists()
+}
+
+// bevy can't fall back glyph by glyph, so it's one font for everything. a language that
+// needs glyphs fira mono hasn't got lists a font that has them, and if that isn't in the
+// assets folder it gets fira mono and a warning instead of not starting
+fn pick_font(language: &Language) -> String {
+    match language.fonts.iter().find(|font| font_exists(font)) {
+        Some(font) => font.clone(),
+        None => {
+            if language.fonts.iter().any(|font| font != DEFAULT_FONT) {
+                warn!(
+                    "none of {} are in the assets folder, {} may not draw properly",
+                    language.fonts.join(", "),
+                    language.name
+                );
+            }
+
+            DEFAULT_FONT.to_string()
+        }
+    }
+}
+
+// every language found, english first, and which one is in use
+pub struct Locale {
+    pub list: Vec<Language>,
+    pub current: usize,
+    font: String,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            list: vec![parse_language("en", ENGLISH).expect("built-in language should parse")],
+            current: 0,
+            font: DEFAULT_FONT.to_string(),
+        }
+    }
+}
+
+impl Locale {
+    pub fn language(&self) -> &Language {
+        &self.list[self.current]
+    }
+
+    pub fn select(&mut self, i: usize) {
+        self.current = i.min(self.list.len() - 1);
+        self.font = pick_font(&self.list[self.current]);
+    }
+
+    pub fn font(&self) -> &str {
+        &self.font
+    }
+
+    // a language that needs a font nobody's put in the assets folder would be nothing but
+    // empty boxes, so it's left out of the list until one is. no fonts listed is fira mono
+    pub fn add(&mut self, language: Language) -> Result<(), String> {
+        if !language.fonts.is_empty() && !language.fonts.iter().any(|font| font_exists(font)) {
+            return Err(format!(
+                "it needs one of {} in the assets folder",
+                language.fonts.join(", ")
+            ));
+        }
+
+        self.list.push(language);
+        Ok(())
+    }
+
+    // the current language, then english, then the key itself so a typo shows up on screen
+    pub fn get(&self, key: &str) -> String {
+        self.language()
+            .get(key)
+            .or_else(|| self.list[0].get(key))
+            .unwrap_or(key)
+            .to_string()
+    }
+
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        args.iter().fold(self.get(key), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+    }
+
+    pub fn on_off(&self, on: bool) -> String {
+        self.get(ifelse!(on, "common.on", "common.off"))
+    }
+}
+
+// english from the binary, the rest alphabetically. broken files are skipped with a warning
+fn load_locale() -> Locale {
+    let dir = FileAssetIo::get_base_path().join(LANG_DIR);
+
+    let mut paths = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+                .filter(|path| !path.ends_with("en.txt"))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+
+    paths.sort();
+
+    let mut locale = Locale::default();
+
+    for path in paths {
+        let code = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let language = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| parse_language(&code, &s));
+
+        let added = language.and_then(|language| {
+            // english has every key, so anything it hasn't got is a typo
+            for key in language.keys().filter(|key| locale.list[0].get(key).is_none()) {
+                warn!("{} has {}, which isn't a key", path.display(), key);
+            }
+
+            locale.add(language)
+        });
+
+        if let Err(e) = added {
+            warn!("skipping language {}: {}", path.display(), e);
+        }
+    }
+
+    locale
+}
+
+fn language_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut locale: ResMut<Locale>,
+    mut config: ResMut<Config>,
+) {
+    for event in reader.iter() {
+        let step = match event {
+            MenuEvent::Adjust("language", step) => *step,
+            MenuEvent::Activate("language") => 1,
+            _ => continue,
+        };
+
+        let count = locale.list.len() as i32;
+        let next = (locale.current as i32 + step).rem_euclid(count) as usize;
+
+        locale.select(next);
+        config.language = locale.language().code.clone();
+    }
+
+    if menu.open {
+        let label = locale.format("settings.language", &[("name", locale.language().name.clone())]);
+        entries.set(MenuPage::Settings, "language", 5, label);
+    }
+}
+
+// every bit of text on screen gets the language's font when it changes.
+// whatever spawns text starts it off with the right one
+fn apply_font(
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+    mut texts: Query<&mut Text>,
+) {
+    if !locale.is_changed() || locale.is_added() {
+        return;
+    }
+
+    let font = asset_server.load(locale.font());
+
+    for mut text in &mut texts {
+        for section in text.sections.iter_mut() {
+            section.style.font = font.clone();
+        }
+    }
+}
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own
+        if !app.world.contains_resource::<Locale>() {
+            let mut locale = load_locale();
+
+            // pick up where the player left off, if that language's still around
+            let saved = app.world.get_resource::<Config>().map(|config| config.language.clone());
+            let i = locale.list.iter().position(|l| Some(&l.code) == saved.as_ref());
+            locale.select(i.unwrap_or(0));
+
+            app.insert_resource(locale);
+        }
+
+        app.add_system(language_menu).add_system(apply_font.after(language_menu));
+    }
+}
@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+
+use crate::{layout::BoardLayout, locale::Locale};
+
+const MENU_KEY: KeyCode = KeyCode::Escape;
+
+// menu text is sized in tiles, same as the score
+const MENU_FONT_TILES: f32 = 1.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MenuPage {
+    #[default]
+    Main,
+    Settings,
+    History,
+    Stats,
+}
+
+// the pause menu. features register their own entries and listen for MenuEvents,
+// so the menu itself doesn't need to know what any of them do
+#[derive(Default)]
+pub struct Menu {
+    pub open: bool,
+    pub page: MenuPage,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn show(&mut self, page: MenuPage) {
+        self.page = page;
+        self.selected = 0;
+    }
+}
+
+pub struct MenuEntry {
+    pub page: MenuPage,
+    pub id: &'static str,
+    pub label: String,
+    order: i32,
+}
+
+#[derive(Default)]
+pub struct MenuEntries(Vec<MenuEntry>);
+
+impl MenuEntries {
+    // adds the entry the first time, afterwards just updates the label.
+    // entries are listed by order, lowest first
+    pub fn set(&mut self, page: MenuPage, id: &'static str, order: i32, label: impl Into<String>) {
+        let label = label.into();
+
+        match self.0.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => entry.label = label,
+            None => {
+                self.0.push(MenuEntry {
+                    page,
+                    id,
+                    label,
+                    order,
+                });
+                self.0.sort_by_key(|entry| entry.order);
+            }
+        }
+    }
+
+    fn on_page(&self, page: MenuPage) -> Vec<&MenuEntry> {
+        self.0.iter().filter(|entry| entry.page == page).collect()
+    }
+}
+
+// activate is enter/space, adjust is left/right with -1/+1
+pub enum MenuEvent {
+    Activate(&'static str),
+    Adjust(&'static str, i32),
+}
+
+#[derive(Component)]
+struct MenuText;
+
+#[derive(Component)]
+struct MenuBackdrop;
+
+fn setup_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut entries: ResMut<MenuEntries>,
+    locale: Res<Locale>,
+) {
+    set_labels(&mut entries, &locale);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.0, 0.0, 0.0, 0.75),
+                // big enough to cover any window
+                custom_size: Some(Vec2::splat(100_000.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 90.0),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(MenuBackdrop);
+
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::from_section("", style).with_alignment(TextAlignment::CENTER),
+            transform: Transform::from_xyz(0.0, 0.0, 100.0),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(MenuText);
+}
+
+fn menu_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut menu: ResMut<Menu>,
+    entries: Res<MenuEntries>,
+    mut writer: EventWriter<MenuEvent>,
+) {
+    let pressed = |a: KeyCode, b: KeyCode| {
+        keyboard_input.just_pressed(a) || keyboard_input.just_pressed(b)
+    };
+
+    if keyboard_input.just_pressed(MENU_KEY) {
+        // escape backs out of a sub-page before closing
+        if menu.open && menu.page != MenuPage::Main {
+            menu.show(MenuPage::Main);
+        } else {
+            menu.open = !menu.open;
+            menu.show(MenuPage::Main);
+        }
+        return;
+    }
+
+    let page = entries.on_page(menu.page);
+
+    if !menu.open || page.is_empty() {
+        return;
+    }
+
+    let count = page.len();
+
+    if pressed(KeyCode::Up, KeyCode::W) {
+        menu.selected = (menu.selected + count - 1) % count;
+    } else if pressed(KeyCode::Down, KeyCode::S) {
+        menu.selected = (menu.selected + 1) % count;
+    }
+
+    let id = page[menu.selected.min(count - 1)].id;
+
+    if pressed(KeyCode::Left, KeyCode::A) {
+        writer.send(MenuEvent::Adjust(id, -1));
+    } else if pressed(KeyCode::Right, KeyCode::D) {
+        writer.send(MenuEvent::Adjust(id, 1));
+    } else if pressed(KeyCode::Return, KeyCode::Space) {
+        writer.send(MenuEvent::Activate(id));
+    }
+}
+
+// the entries the menu owns itself
+fn set_labels(entries: &mut MenuEntries, locale: &Locale) {
+    entries.set(MenuPage::Main, "resume", 0, locale.get("menu.resume"));
+    entries.set(MenuPage::Main, "settings", 90, locale.get("menu.settings"));
+    entries.set(MenuPage::Settings, "back", 1000, locale.get("common.back"));
+}
+
+fn menu_actions(
+    mut menu: ResMut<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        match event {
+            MenuEvent::Activate("resume") => menu.open = false,
+            MenuEvent::Activate("settings") => menu.show(MenuPage::Settings),
+            MenuEvent::Activate("back") => menu.show(MenuPage::Main),
+            _ => (),
+        }
+    }
+
+    if locale.is_changed() {
+        set_labels(&mut entries, &locale);
+    }
+}
+
+fn draw_menu(
+    menu: Res<Menu>,
+    entries: Res<MenuEntries>,
+    layout: Res<BoardLayout>,
+    mut text: Query<(&mut Text, &mut Visibility), (With<MenuText>, Without<MenuBackdrop>)>,
+    mut backdrop: Query<&mut Visibility, With<MenuBackdrop>>,
+) {
+    for mut visibility in &mut backdrop {
+        visibility.is_visible = menu.open;
+    }
+
+    for (mut text, mut visibility) in &mut text {
+        visibility.is_visible = menu.open;
+
+        if !menu.open {
+            continue;
+        }
+
+        text.sections[0].value = entries
+            .on_page(menu.page)
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                ifelse!(
+                    i == menu.selected,
+                    format!("> {} <", entry.label),
+                    entry.label.clone()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        text.sections[0].style.font_size = MENU_FONT_TILES * layout.tile_size;
+    }
+}
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Menu::default())
+            .insert_resource(MenuEntries::default())
+            .add_event::<MenuEvent>()
+            .add_startup_system(setup_menu)
+            .add_system(menu_controls)
+            .add_system(menu_actions.after(menu_controls))
+            .add_system(draw_menu.after(menu_actions));
+    }
+}
@@ -0,0 +1,563 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf, time::Duration};
+
+use crate::{
+    config::Config,
+    creatures::{spawn_creatures, Creature, RivalBody},
+    daily::{daily_rules, Date},
+    draw_bg_element,
+    levels::{Level, Levels},
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    occupancy::{Cell, OccupancyGrid},
+    portals::{spawn_portal, Portal},
+    rng::GameRng,
+    spawn_snake,
+    theme::Theme,
+    tick::{TickClock, TickStage, TickStageLabel},
+    DirectionPair, Food, GameOver, Obstacle, Position, Score,
+    SnakeDirection, SnakeHead, SnakeSegment, SnakeSegments, SpriteSheet, GRID_HEIGHT, GRID_WIDTH,
+};
+
+const HIGH_SCORES_FILE: &str = "highscores.ron";
+
+// survival adds a segment this often, whether you've eaten or not
+pub const SURVIVAL_GROW_TICKS: u64 = 40;
+
+/// which rules a round is played by
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameMode {
+    // eat, grow, bumping into things costs the tail
+    #[default]
+    Classic,
+    // as much food as possible before the clock (in seconds) runs out
+    TimeAttack(u64),
+    // grows by itself, any bump is fatal, score is seconds alive
+    Survival,
+    // bumps cost the tail but never the head
+    Zen,
+    // classic rules on today's board, which is the same board for everyone
+    Daily,
+    // classic rules on a board from a level file
+    Levels,
+    // a fixed board and fixed food, eat it all. any bump is fatal
+    Puzzle,
+}
+
+// what the menu cycles through, in order
+const MODES: [GameMode; 8] = [
+    GameMode::Classic,
+    GameMode::TimeAttack(60),
+    GameMode::TimeAttack(120),
+    GameMode::Survival,
+    GameMode::Zen,
+    GameMode::Daily,
+    GameMode::Levels,
+    GameMode::Puzzle,
+];
+
+impl GameMode {
+    // also what high scores are filed under
+    pub fn name(self) -> String {
+        match self {
+            GameMode::Classic => "classic".to_string(),
+            GameMode::TimeAttack(secs) => format!("time attack {}s", secs),
+            GameMode::Survival => "survival".to_string(),
+            GameMode::Zen => "zen".to_string(),
+            GameMode::Daily => "daily".to_string(),
+            GameMode::Levels => "levels".to_string(),
+            GameMode::Puzzle => "puzzle".to_string(),
+        }
+    }
+
+    // what the high scores are kept under. every puzzle gets its own
+    pub fn score_key(self, puzzle: &str) -> String {
+        match self {
+            GameMode::Puzzle if !puzzle.is_empty() => format!("puzzle: {}", puzzle),
+            _ => self.name(),
+        }
+    }
+
+    // what the player sees, in their language. the name stays english for the high scores
+    pub fn label(self, locale: &Locale) -> String {
+        match self {
+            GameMode::TimeAttack(secs) => {
+                locale.format("mode.time_attack", &[("secs", secs.to_string())])
+            }
+            _ => locale.get(&format!("mode.{}", self.name())),
+        }
+    }
+
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            GameMode::TimeAttack(secs) => Some(Duration::from_secs(secs)),
+            _ => None,
+        }
+    }
+
+    pub fn bumps_kill(self) -> bool {
+        matches!(self, GameMode::Survival | GameMode::Puzzle)
+    }
+
+    pub fn can_die(self) -> bool {
+        self != GameMode::Zen
+    }
+
+    // how many ticks back the player can rewind. zen is the practice mode and can go
+    // all the way back, anything ranked can't at all, classic is up to the player
+    pub fn rewind_limit(self, casual: usize) -> usize {
+        match self {
+            GameMode::Zen => usize::MAX,
+            GameMode::Classic | GameMode::Levels | GameMode::Puzzle => casual,
+            GameMode::TimeAttack(_) | GameMode::Survival | GameMode::Daily => 0,
+        }
+    }
+
+    // the next or previous mode in the menu, wrapping around.
+    // anything that isn't in the list (an old time limit from a config file) starts over
+    fn cycle(self, step: i32) -> GameMode {
+        let count = MODES.len() as i32;
+        let i = MODES.iter().position(|mode| *mode == self).unwrap_or(0) as i32;
+
+        MODES[(i + step).rem_euclid(count) as usize]
+    }
+}
+
+/// the board a round is played on
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Rules {
+    pub width: i32,
+    pub height: i32,
+    pub walls: Vec<Position>,
+    // odds of any one food being a bonus
+    pub bonus_chance: f64,
+    // things that move around on their own
+    #[serde(default)]
+    pub creatures: Vec<Creature>,
+    // food that runs away, instead of the usual kind
+    #[serde(default)]
+    pub mice: Vec<Position>,
+    // pairs of cells, walking into one puts you out the other side of its partner
+    #[serde(default)]
+    pub portals: Vec<(Position, Position)>,
+    // where the snake starts, head first. empty is the usual spot
+    #[serde(default)]
+    pub snake: Vec<(Position, DirectionPair)>,
+    // fixed food, put down one at a time in this order instead of at random
+    #[serde(default)]
+    pub food: Vec<Position>,
+    // how many moves there are to eat it all in
+    #[serde(default)]
+    pub move_limit: Option<u64>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            width: GRID_WIDTH,
+            height: GRID_HEIGHT,
+            walls: Vec::new(),
+            bonus_chance: 0.0,
+            creatures: Vec::new(),
+            mice: Vec::new(),
+            portals: Vec::new(),
+            snake: Vec::new(),
+            food: Vec::new(),
+            move_limit: None,
+        }
+    }
+}
+
+impl Rules {
+    fn partner(&self, pos: Position) -> Option<Position> {
+        self.portals.iter().find_map(|(a, b)| match pos {
+            _ if pos == *a => Some(*b),
+            _ if pos == *b => Some(*a),
+            _ => None,
+        })
+    }
+
+    // where a step from pos actually lands, going through any portals on the way.
+    // portals lined up back to back are followed at most once each
+    pub fn step(&self, pos: Position, dir: SnakeDirection) -> Position {
+        let mut next = pos.step(dir);
+
+        for _ in 0..self.portals.len() {
+            match self.partner(next) {
+                Some(exit) => next = exit.step(dir),
+                None => break,
+            }
+        }
+
+        next
+    }
+}
+
+/// the game being played right now. the clock only starts once the snake does
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Round {
+    pub mode: GameMode,
+    pub rules: Rules,
+    // where the food rng started
+    pub seed: u64,
+    // which day's board this is, for dailies
+    pub date: Option<Date>,
+    // and which puzzle, by name, for puzzles
+    #[serde(default)]
+    pub puzzle: String,
+    pub ticks: u64,
+    pub elapsed: Duration,
+}
+
+impl Round {
+    fn new(mode: GameMode, level: &Level) -> Self {
+        match mode {
+            GameMode::Levels => Round {
+                mode,
+                rules: level.rules.clone(),
+                seed: thread_rng().gen(),
+                ..default()
+            },
+            GameMode::Daily => {
+                let date = Date::today();
+                let (seed, rules) = daily_rules(date);
+
+                Round {
+                    mode,
+                    rules,
+                    seed,
+                    date: Some(date),
+                    ..default()
+                }
+            }
+            _ => Round {
+                mode,
+                seed: thread_rng().gen(),
+                ..default()
+            },
+        }
+    }
+
+    // whether survival wants the snake a bit longer this tick
+    pub fn grow_due(&self) -> bool {
+        self.mode == GameMode::Survival && self.ticks > 0 && self.ticks.is_multiple_of(SURVIVAL_GROW_TICKS)
+    }
+
+    pub fn time_left(&self) -> Option<Duration> {
+        self.mode
+            .time_limit()
+            .map(|limit| limit.saturating_sub(self.elapsed))
+    }
+
+    pub fn score_key(&self) -> String {
+        self.mode.score_key(&self.puzzle)
+    }
+
+    pub fn moves_left(&self) -> Option<u64> {
+        self.rules
+            .move_limit
+            .map(|limit| limit.saturating_sub(self.ticks))
+    }
+}
+
+pub struct TimeUpEvent;
+
+// the last move of a puzzle's been made
+pub struct OutOfMovesEvent;
+
+// enough to play a round back: where the rng started, and which way the snake
+// turned on which tick
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub turns: Vec<(u64, SnakeDirection)>,
+}
+
+// throws away the current game and starts the mode picked in the config
+pub struct NewGameEvent;
+
+// the round the next new game plays instead of a fresh one, for playing a recording back
+// or starting a puzzle
+#[derive(Default)]
+pub struct NextRound(pub Option<Round>);
+
+// best score per mode, kept next to the config
+#[derive(Default)]
+pub struct HighScores {
+    best: BTreeMap<String, u128>,
+    // nothing's saved without a path, so tests can't clobber the real file
+    path: Option<PathBuf>,
+    dirty: bool,
+}
+
+impl HighScores {
+    // by GameMode::score_key
+    pub fn best(&self, key: &str) -> u128 {
+        self.best.get(key).copied().unwrap_or(0)
+    }
+
+    fn submit(&mut self, key: String, score: u128) {
+        if score > self.best(&key) {
+            self.best.insert(key, score);
+            self.dirty = true;
+        }
+    }
+
+    fn save(&mut self) {
+        let path = match (&self.path, self.dirty) {
+            (Some(path), true) => path,
+            _ => return,
+        };
+
+        let result = ron::ser::to_string_pretty(&self.best, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|s| fs::write(path, s).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            warn!("couldn't save {}: {}", path.display(), e);
+        }
+
+        // win or lose, don't try again until there's something new
+        self.dirty = false;
+    }
+}
+
+fn load_high_scores() -> HighScores {
+    let path = FileAssetIo::get_base_path().join(HIGH_SCORES_FILE);
+
+    let best = match fs::read_to_string(&path) {
+        Ok(s) => ron::from_str(&s).unwrap_or_else(|e| {
+            warn!("couldn't read {}: {}, starting fresh", path.display(), e);
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    };
+
+    HighScores {
+        best,
+        path: Some(path),
+        dirty: false,
+    }
+}
+
+fn start_first_game(mut writer: EventWriter<NewGameEvent>) {
+    writer.send(NewGameEvent);
+}
+
+// an empty board with the walls and portals in, snake and food not included
+pub fn build_board(commands: &mut Commands, rules: &Rules, theme: &Theme) -> OccupancyGrid {
+    let mut grid = OccupancyGrid::new(rules.width, rules.height);
+
+    for wall in rules.walls.iter() {
+        grid.set(wall.x, wall.y, Cell::Wall);
+
+        let ent = draw_bg_element(wall.x, wall.y, 1.0, 1.0, theme.palette.outline, commands);
+        commands.entity(ent).insert(Obstacle);
+    }
+
+    for (i, (a, b)) in rules.portals.iter().enumerate() {
+        for pos in [a, b] {
+            grid.set(pos.x, pos.y, Cell::Portal);
+            spawn_portal(commands, *pos, i, theme);
+        }
+    }
+
+    grid
+}
+
+pub fn new_game(
+    mut commands: Commands,
+    mut reader: EventReader<NewGameEvent>,
+    config: Res<Config>,
+    pieces: Query<
+        Entity,
+        Or<(
+            With<SnakeSegment>,
+            With<Food>,
+            With<Obstacle>,
+            With<Creature>,
+            With<RivalBody>,
+            With<Portal>,
+        )>,
+    >,
+    mut segments: ResMut<SnakeSegments>,
+    mut grid: ResMut<OccupancyGrid>,
+    mut score: ResMut<Score>,
+    mut game_over: ResMut<GameOver>,
+    mut clock: ResMut<TickClock>,
+    mut round: ResMut<Round>,
+    mut rng: ResMut<GameRng>,
+    mut replay: ResMut<Replay>,
+    mut next_round: ResMut<NextRound>,
+    levels: Res<Levels>,
+    sprite_sheet: Res<SpriteSheet>,
+    theme: Res<Theme>,
+) {
+    if reader.iter().last().is_none() {
+        return;
+    }
+
+    for ent in pieces.iter() {
+        commands.entity(ent).despawn();
+    }
+
+    let next = match next_round.0.take() {
+        // from the start, whatever point it was taken at
+        Some(round) => Round {
+            ticks: 0,
+            elapsed: Duration::ZERO,
+            ..round
+        },
+        None => Round::new(config.mode, levels.current()),
+    };
+
+    *grid = build_board(&mut commands, &next.rules, &theme);
+    spawn_creatures(&mut commands, &mut grid, &next.rules, &sprite_sheet, &theme);
+    *segments = spawn_snake(&mut commands, &mut grid, &next.rules.snake, &sprite_sheet, &theme);
+    *score = Score(0);
+    *game_over = GameOver(None);
+    *clock = TickClock::default();
+    *rng = GameRng::new(next.seed);
+    *replay = Replay {
+        seed: next.seed,
+        turns: Vec::new(),
+    };
+    *round = next;
+}
+
+pub fn advance_round(
+    mut round: ResMut<Round>,
+    clock: Res<TickClock>,
+    mut score: ResMut<Score>,
+    heads: Query<&DirectionPair, With<SnakeHead>>,
+    mut time_up_writer: EventWriter<TimeUpEvent>,
+    mut out_of_moves_writer: EventWriter<OutOfMovesEvent>,
+) {
+    // nothing counts until the player gets going
+    if heads.iter().all(|dir| dir.1 == SnakeDirection::Null) {
+        return;
+    }
+
+    round.ticks += 1;
+    round.elapsed += clock.step;
+
+    if round.mode == GameMode::Survival {
+        score.0 = round.elapsed.as_secs() as u128;
+    }
+
+    if round.time_left() == Some(Duration::ZERO) {
+        time_up_writer.send(TimeUpEvent);
+    }
+
+    if round.moves_left() == Some(0) {
+        out_of_moves_writer.send(OutOfMovesEvent);
+    }
+}
+
+// every change of direction, by tick
+fn record_turns(
+    round: Res<Round>,
+    mut replay: ResMut<Replay>,
+    heads: Query<&DirectionPair, With<SnakeHead>>,
+) {
+    for dir in heads.iter() {
+        let last = replay.turns.last().map(|(_, last)| *last);
+
+        if dir.1 != SnakeDirection::Null && last != Some(dir.1) {
+            replay.turns.push((round.ticks, dir.1));
+        }
+    }
+}
+
+fn track_high_score(round: Res<Round>, score: Res<Score>, mut high_scores: ResMut<HighScores>) {
+    if score.is_changed() {
+        high_scores.submit(round.score_key(), score.0);
+    }
+}
+
+// written out when a game ends or the menu comes up, rather than on every point
+fn save_high_scores(
+    mut high_scores: ResMut<HighScores>,
+    game_over: Res<GameOver>,
+    menu: Res<Menu>,
+) {
+    if high_scores.dirty && (game_over.0.is_some() || menu.open) {
+        high_scores.save();
+    }
+}
+
+pub(crate) fn modes_menu(
+    mut menu: ResMut<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut config: ResMut<Config>,
+    high_scores: Res<HighScores>,
+    mut writer: EventWriter<NewGameEvent>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        match event {
+            MenuEvent::Activate("new_game") => {
+                writer.send(NewGameEvent);
+                menu.open = false;
+            }
+            MenuEvent::Adjust("mode", n) => config.mode = config.mode.cycle(*n),
+            MenuEvent::Activate("mode") => config.mode = config.mode.cycle(1),
+            _ => (),
+        }
+    }
+
+    if menu.open {
+        entries.set(MenuPage::Main, "new_game", 10, locale.get("menu.new_game"));
+        entries.set(
+            MenuPage::Main,
+            "mode",
+            20,
+            locale.format(
+                "menu.mode",
+                &[
+                    ("mode", config.mode.label(&locale)),
+                    ("best", high_scores.best(&config.mode.score_key(&config.puzzle)).to_string()),
+                ],
+            ),
+        );
+    }
+}
+
+/// new games, the Round, the modes' own rules and the high scores
+pub struct ModePlugin;
+
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own, so they don't read or write the real file
+        if !app.world.contains_resource::<HighScores>() {
+            app.insert_resource(load_high_scores());
+        }
+
+        app.insert_resource(Round::default())
+            .insert_resource(Replay::default())
+            .insert_resource(GameRng::default())
+            .insert_resource(NextRound::default())
+            .add_event::<NewGameEvent>()
+            .add_event::<TimeUpEvent>()
+            .add_event::<OutOfMovesEvent>()
+            .add_startup_system(start_first_game)
+            .add_system(modes_menu)
+            .add_system(new_game.after(modes_menu))
+            .add_system_to_stage(CoreStage::Last, save_high_scores)
+            .stage(TickStageLabel, |stage: &mut TickStage| {
+                stage.add_system_set(
+                    SystemSet::new()
+                        .with_system(advance_round.before(crate::snake_movement))
+                        .with_system(
+                            record_turns
+                                .after(advance_round)
+                                .before(crate::snake_movement),
+                        )
+                        .with_system(track_high_score.after(crate::scored).before(crate::end_game)),
+                )
+            });
+    }
+}
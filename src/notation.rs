@@ -0,0 +1,340 @@
+// boards as text, for test fixtures, bug reports and level files.
+// see parse_board for what the notation looks like
+
+use std::collections::HashMap;
+
+use crate::{
+    creatures::Creature, modes::Rules, raster::Frame, DirectionPair, FoodKind, Position,
+    SnakeDirection,
+};
+
+use SnakeDirection::*;
+
+const PORTAL_MARKS: &[u8] = b"0123456789";
+
+fn head_mark(dir: SnakeDirection) -> char {
+    match dir {
+        Up => 'U',
+        Down => 'D',
+        Left => 'L',
+        Right => 'R',
+        Null => '@',
+    }
+}
+
+fn body_mark(dir: SnakeDirection) -> char {
+    match dir {
+        Up => '^',
+        Down => 'v',
+        Left => '<',
+        Right => '>',
+        Null => 'o',
+    }
+}
+
+// the other way round: whether it's the head, and which way it's going
+fn snake_mark(mark: char) -> Option<(bool, SnakeDirection)> {
+    match mark {
+        'U' => Some((true, Up)),
+        'D' => Some((true, Down)),
+        'L' => Some((true, Left)),
+        'R' => Some((true, Right)),
+        '@' => Some((true, Null)),
+        '^' => Some((false, Up)),
+        'v' => Some((false, Down)),
+        '<' => Some((false, Left)),
+        '>' => Some((false, Right)),
+        'o' => Some((false, Null)),
+        _ => None,
+    }
+}
+
+fn print_pos(pos: Position) -> String {
+    format!("{},{}", pos.x, pos.y)
+}
+
+pub(crate) fn parse_pos(s: &str) -> Result<Position, String> {
+    let (x, y) = s.split_once(',').ok_or_else(|| format!("{} isn't an x,y", s))?;
+
+    match (x.trim().parse(), y.trim().parse()) {
+        (Ok(x), Ok(y)) => Ok(Position { x, y }),
+        _ => Err(format!("{} isn't an x,y", s)),
+    }
+}
+
+/// a board in the text notation, see parse_board for what it looks like
+pub fn print_board(frame: &Frame) -> String {
+    let rules = &frame.rules;
+    let (width, height) = (rules.width.max(0) as usize, rules.height.max(0) as usize);
+    let mut grid = vec![vec!['.'; width]; height];
+    let mut lines = Vec::new();
+
+    let mut mark = |pos: Position, mark: char| {
+        if pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < width && (pos.y as usize) < height {
+            grid[height - 1 - pos.y as usize][pos.x as usize] = mark;
+        }
+    };
+
+    for wall in rules.walls.iter() {
+        mark(*wall, '#');
+    }
+
+    for (i, (a, b)) in rules.portals.iter().enumerate() {
+        match PORTAL_MARKS.get(i) {
+            Some(digit) => {
+                mark(*a, *digit as char);
+                mark(*b, *digit as char);
+            }
+            None => lines.push(format!("portal {} {}", print_pos(*a), print_pos(*b))),
+        }
+    }
+
+    for (pos, kind) in frame.food.iter() {
+        match kind {
+            FoodKind::Normal => mark(*pos, '*'),
+            FoodKind::Bonus => mark(*pos, '$'),
+            FoodKind::Mouse => mark(*pos, 'm'),
+            FoodKind::Custom { id, points } => {
+                mark(*pos, '?');
+                lines.push(format!("custom {} {} {}", print_pos(*pos), id, points));
+            }
+        }
+    }
+
+    for (pos, creature) in frame.creatures.iter() {
+        match creature {
+            Creature::Patrol { .. } => mark(*pos, 'B'),
+            Creature::Rival { body, .. } => body.iter().for_each(|cell| mark(*cell, 'x')),
+        }
+
+        // one line, whatever's in it
+        let ron = ron::to_string(creature).expect("creatures should serialize");
+        lines.push(format!("creature {} {}", print_pos(*pos), ron));
+    }
+
+    for (i, (pos, dir)) in frame.snake.iter().enumerate() {
+        mark(*pos, ifelse!(i == 0, head_mark(dir.1), body_mark(dir.1)));
+    }
+
+    let mut out = format!("board {}x{} score {}\n", rules.width, rules.height, frame.score);
+
+    for row in grid {
+        out.extend(row);
+        out.push('\n');
+    }
+
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    out
+}
+
+// which of the snake's cells comes after the one at pos, going head to tail.
+// a moving segment points at the one in front of it, through portals and all.
+// one that isn't moving yet just has to be next door
+fn behind(
+    pos: Position,
+    cells: &HashMap<(i32, i32), SnakeDirection>,
+    rules: &Rules,
+) -> Result<Option<Position>, String> {
+    let candidates = cells
+        .iter()
+        .map(|((x, y), dir)| (Position { x: *x, y: *y }, *dir))
+        .filter(|(cell, dir)| match dir {
+            Null => (cell.x - pos.x).abs() + (cell.y - pos.y).abs() == 1,
+            _ => rules.step(*cell, *dir) == pos,
+        })
+        .map(|(cell, _)| cell)
+        .collect::<Vec<Position>>();
+
+    match candidates[..] {
+        [] => Ok(None),
+        [next] => Ok(Some(next)),
+        _ => Err(format!("can't tell which way the snake goes after {}", print_pos(pos))),
+    }
+}
+
+/// reads the text notation back into a board:
+///
+/// ```text
+/// board 6x4 score 2
+/// ......
+/// .>>D..
+/// .^..#.
+/// .^..*.
+/// ```
+///
+/// a header with the size and score, then the rows as they look on screen, top first.
+/// `#` is a wall, `*` `$` `m` and `?` are food (normal, bonus, mouse and custom) and the
+/// digits are portals, each one twice. the head is `U` `D` `L` `R` (`@` before it moves)
+/// and the rest of the snake is `^` `v` `<` `>`, the way each piece is heading (`o` before
+/// it moves). `B` and `x` mark patrols and rivals, but it's the lines after the rows that
+/// count for those: `creature x,y (...)` in ron. custom food gets a `custom x,y id points`
+/// line, and portals past the tenth pair a `portal x,y x,y` one.
+/// whitespace around lines and blank lines don't matter
+pub fn parse_board(text: &str) -> Result<Frame, String> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let (n, header) = lines.next().ok_or("there's no board")?;
+    let at = |n: usize, e: String| format!("line {}: {}", n, e);
+
+    let mut words = header.split_whitespace();
+    let size = match (words.next(), words.next()) {
+        (Some("board"), Some(size)) => size,
+        _ => return Err(at(n, "it should start with board WxH".to_string())),
+    };
+
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<i32>().ok()?, h.parse::<i32>().ok()?)))
+        .filter(|(w, h)| *w > 0 && *h > 0)
+        .ok_or_else(|| at(n, format!("{} isn't a size", size)))?;
+
+    let score = match (words.next(), words.next()) {
+        (Some("score"), Some(score)) => score
+            .parse()
+            .map_err(|_| at(n, format!("{} isn't a score", score)))?,
+        (None, _) => 0,
+        _ => return Err(at(n, "only the score goes after the size".to_string())),
+    };
+
+    let mut frame = Frame {
+        rules: Rules {
+            width,
+            height,
+            ..Rules::default()
+        },
+        score,
+        ..Frame::default()
+    };
+
+    let mut portal_ends: Vec<Vec<Position>> = vec![Vec::new(); PORTAL_MARKS.len()];
+    let mut customs = Vec::new();
+    let mut head = None;
+    let mut body = HashMap::new();
+
+    for row in 0..height {
+        let (n, line) = lines
+            .next()
+            .ok_or_else(|| format!("the board's {} rows short", height - row))?;
+
+        if line.chars().count() != width as usize {
+            return Err(at(n, format!("rows should be {} wide", width)));
+        }
+
+        for (x, mark) in line.chars().enumerate() {
+            let pos = Position {
+                x: x as i32,
+                y: height - 1 - row,
+            };
+
+            match mark {
+                '.' | 'B' | 'x' => (),
+                '#' => frame.rules.walls.push(pos),
+                '*' => frame.food.push((pos, FoodKind::Normal)),
+                '$' => frame.food.push((pos, FoodKind::Bonus)),
+                'm' => frame.food.push((pos, FoodKind::Mouse)),
+                '?' => customs.push(pos),
+                '0'..='9' => portal_ends[mark as usize - '0' as usize].push(pos),
+                _ => match snake_mark(mark) {
+                    Some((true, _)) if head.is_some() => {
+                        return Err(at(n, "there's more than one head".to_string()))
+                    }
+                    Some((true, dir)) => head = Some((pos, dir)),
+                    Some((false, dir)) => {
+                        body.insert((pos.x, pos.y), dir);
+                    }
+                    None => return Err(at(n, format!("don't know what {} is", mark))),
+                },
+            }
+        }
+    }
+
+    for (i, ends) in portal_ends.iter().enumerate() {
+        match ends[..] {
+            [] => (),
+            [a, b] => frame.rules.portals.push((a, b)),
+            _ => return Err(format!("portal {} needs exactly two ends", i)),
+        }
+    }
+
+    for (n, line) in lines {
+        let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut words = rest.split_whitespace();
+
+        match kind {
+            "custom" => {
+                let mut next = || words.next().ok_or_else(|| at(n, "custom x,y id points".to_string()));
+                let pos = parse_pos(next()?).map_err(|e| at(n, e))?;
+                let (id, points) = (next()?, next()?);
+
+                let kind = match (id.parse(), points.parse()) {
+                    (Ok(id), Ok(points)) => FoodKind::Custom { id, points },
+                    _ => return Err(at(n, "custom x,y id points".to_string())),
+                };
+
+                if !customs.contains(&pos) {
+                    return Err(at(n, format!("there's no ? at {}", print_pos(pos))));
+                }
+
+                customs.retain(|custom| *custom != pos);
+                frame.food.push((pos, kind));
+            }
+            "portal" => match (words.next(), words.next()) {
+                (Some(a), Some(b)) => {
+                    let a = parse_pos(a).map_err(|e| at(n, e))?;
+                    let b = parse_pos(b).map_err(|e| at(n, e))?;
+                    frame.rules.portals.push((a, b));
+                }
+                _ => return Err(at(n, "portal x,y x,y".to_string())),
+            },
+            "creature" => {
+                let (pos, ron) = rest.split_once(' ').unwrap_or((rest, ""));
+                let pos = parse_pos(pos).map_err(|e| at(n, e))?;
+                let creature = ron::from_str::<Creature>(ron).map_err(|e| at(n, e.to_string()))?;
+
+                frame.creatures.push((pos, creature));
+            }
+            _ => return Err(at(n, format!("don't know what {} is", kind))),
+        }
+    }
+
+    if let Some(pos) = customs.first() {
+        return Err(format!("the ? at {} needs a custom line", print_pos(*pos)));
+    }
+
+    // head first, each piece found from the one in front of it
+    if let Some((pos, dir)) = head {
+        let mut snake = vec![(pos, dir)];
+
+        while let Some(next) = behind(snake.last().unwrap().0, &body, &frame.rules)? {
+            let dir = body.remove(&(next.x, next.y)).unwrap();
+            snake.push((next, dir));
+        }
+
+        if let Some((x, y)) = body.keys().next() {
+            return Err(format!("the snake at {},{} isn't joined to the head", x, y));
+        }
+
+        // which way a piece came in is the way the one behind it was going,
+        // which is all the sprites need
+        frame.snake = (0..snake.len())
+            .map(|i| {
+                let (pos, dir) = snake[i];
+                let entry = snake.get(i + 1).map_or(dir, |(_, behind)| *behind);
+
+                (pos, DirectionPair(entry, dir))
+            })
+            .collect();
+    } else if !body.is_empty() {
+        return Err("the snake hasn't got a head".to_string());
+    }
+
+    Ok(frame)
+}
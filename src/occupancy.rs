@@ -0,0 +1,143 @@
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Snake,
+    Food,
+    Wall,
+    // never stood on, you come out of its partner instead. never free, so no food lands on one
+    Portal,
+}
+
+impl Cell {
+    // what it's called in mods, stats and the like
+    pub fn name(self) -> &'static str {
+        match self {
+            Cell::Empty => "empty",
+            Cell::Snake => "snake",
+            Cell::Food => "food",
+            Cell::Wall => "wall",
+            Cell::Portal => "portal",
+        }
+    }
+}
+
+// marks a cell that isn't in the free list
+const NOT_FREE: u32 = u32::MAX;
+
+// what's in every cell of the board, kept up to date as things move instead of
+// being rebuilt every tick. empty cells are also kept in a dense list (swap-removed
+// when they fill up), so picking a random one is a single index instead of a
+// rejection loop that gets slower the fuller the board is
+#[derive(Debug, Clone)]
+pub struct OccupancyGrid {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+    free: Vec<u32>,
+    // where each cell sits in `free`, or NOT_FREE
+    free_slot: Vec<u32>,
+}
+
+impl OccupancyGrid {
+    pub fn new(width: i32, height: i32) -> Self {
+        let len = (width.max(0) * height.max(0)) as usize;
+
+        OccupancyGrid {
+            width,
+            height,
+            cells: vec![Cell::Empty; len],
+            free: (0..len as u32).collect(),
+            free_slot: (0..len as u32).collect(),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x < self.width && y >= 0 && y < self.height
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    fn coords(&self, index: u32) -> (i32, i32) {
+        (index as i32 % self.width, index as i32 / self.width)
+    }
+
+    // off the board counts as wall, so bounds checks come for free
+    pub fn get(&self, x: i32, y: i32) -> Cell {
+        ifelse!(
+            self.in_bounds(x, y),
+            self.cells[self.index(x, y)],
+            Cell::Wall
+        )
+    }
+
+    // returns what used to be there. writes off the board are ignored
+    pub fn set(&mut self, x: i32, y: i32, cell: Cell) -> Cell {
+        if !self.in_bounds(x, y) {
+            return Cell::Wall;
+        }
+
+        let index = self.index(x, y);
+        let old = std::mem::replace(&mut self.cells[index], cell);
+
+        match (old == Cell::Empty, cell == Cell::Empty) {
+            (true, false) => self.take_free(index),
+            (false, true) => self.give_free(index),
+            _ => (),
+        }
+
+        old
+    }
+
+    pub fn is_free(&self, x: i32, y: i32) -> bool {
+        self.get(x, y) == Cell::Empty
+    }
+
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.free.is_empty()
+    }
+
+    // a uniformly random empty cell, or None when there aren't any
+    pub fn sample_free(&self, rng: &mut impl Rng) -> Option<(i32, i32)> {
+        ifelse!(
+            self.free.is_empty(),
+            None,
+            Some(self.coords(self.free[rng.gen_range(0..self.free.len())]))
+        )
+    }
+
+    pub fn clear(&mut self) {
+        *self = OccupancyGrid::new(self.width, self.height);
+    }
+
+    fn take_free(&mut self, index: usize) {
+        let slot = self.free_slot[index] as usize;
+        let last = *self.free.last().unwrap();
+
+        self.free.swap_remove(slot);
+        if last as usize != index {
+            self.free_slot[last as usize] = slot as u32;
+        }
+        self.free_slot[index] = NOT_FREE;
+    }
+
+    fn give_free(&mut self, index: usize) {
+        self.free_slot[index] = self.free.len() as u32;
+        self.free.push(index as u32);
+    }
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+use crate::{theme::Theme, Position, Size};
+
+// one end of a portal pair, and which pair it belongs to
+#[derive(Component)]
+pub struct Portal(pub usize);
+
+// each pair gets its own shade of the theme's portal colour, so you can tell which goes where
+pub fn pair_color(theme: &Theme, pair: usize) -> Color {
+    match theme.palette.portal.as_hsla() {
+        Color::Hsla {
+            hue,
+            saturation,
+            lightness,
+            alpha,
+        } => Color::hsla((hue + pair as f32 * 67.0) % 360.0, saturation, lightness, alpha),
+        color => color,
+    }
+}
+
+// the grid cell's up to the caller, same as the food
+pub fn spawn_portal(commands: &mut Commands, pos: Position, pair: usize, theme: &Theme) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: pair_color(theme, pair),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Portal(pair))
+        .insert(Size::square(0.8))
+        .insert(pos)
+        .id()
+}
+
+fn recolor_portals(theme: Res<Theme>, mut portals: Query<(&mut Sprite, &Portal)>) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    for (mut sprite, portal) in &mut portals {
+        sprite.color = pair_color(&theme, portal.0);
+    }
+}
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(recolor_portals);
+    }
+}
@@ -0,0 +1,376 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    levels::Level,
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    modes::{modes_menu, new_game, GameMode, NewGameEvent, NextRound, Round, Rules},
+    notation::{parse_board, parse_pos},
+    DirectionPair, Position, SnakeDirection,
+};
+
+use SnakeDirection::*;
+
+const PUZZLE_DIR: &str = "assets/puzzles";
+
+// how many positions the solver looks at before it gives up
+const SOLVER_LIMIT: usize = 2_000_000;
+
+// every puzzle found on disk, in menu order, and which one is picked.
+// a puzzle is a level whose rules have the snake, the food and the move limit in
+#[derive(Default)]
+pub struct Puzzles {
+    pub list: Vec<Level>,
+    pub current: usize,
+}
+
+impl Puzzles {
+    pub fn current(&self) -> Option<&Level> {
+        self.list.get(self.current)
+    }
+}
+
+/// a puzzle is a board in the text notation with a couple of lines on top:
+///
+/// ```text
+/// moves 9
+/// food 4,1 1,3
+/// board 6x4
+/// ......
+/// .#....
+/// .@....
+/// .o....
+/// ```
+///
+/// `food` is where each food goes, in the order they're put down, and `moves` is how many
+/// moves there are to eat them all in (leave it out for no limit). the board has the snake
+/// and walls and portals, but no food or creatures. the snake waits for the first key
+/// whichever way it's drawn
+pub fn parse_puzzle(name: &str, text: &str) -> Result<Level, String> {
+    let at = |n: usize, e: String| format!("line {}: {}", n + 1, e);
+    let mut food = Vec::new();
+    let mut move_limit = None;
+    let mut board = Vec::new();
+    let mut in_board = false;
+
+    for (n, line) in text.lines().enumerate() {
+        let (kind, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        in_board = in_board || kind == "board";
+
+        // blanked rather than dropped, so the board's errors have the right line numbers
+        match kind {
+            _ if in_board => board.push(line),
+            "" => board.push(""),
+            "moves" => {
+                let moves = rest
+                    .trim()
+                    .parse()
+                    .map_err(|_| at(n, format!("{} isn't a number of moves", rest)))?;
+                move_limit = Some(moves);
+                board.push("");
+            }
+            "food" => {
+                for pos in rest.split_whitespace() {
+                    food.push(parse_pos(pos).map_err(|e| at(n, e))?);
+                }
+                board.push("");
+            }
+            _ => return Err(at(n, format!("don't know what {} is", kind))),
+        }
+    }
+
+    let frame = parse_board(&board.join("\n"))?;
+    let rules = &frame.rules;
+
+    if frame.snake.len() < 2 {
+        return Err("the snake needs a head and something to steer by".to_string());
+    }
+
+    if !frame.creatures.is_empty() {
+        return Err("puzzles don't have creatures".to_string());
+    }
+
+    if !frame.food.is_empty() {
+        return Err("the food goes in the food line, in order".to_string());
+    }
+
+    if food.is_empty() {
+        return Err("there's no food line".to_string());
+    }
+
+    for pos in food.iter() {
+        let on_board = pos.x >= 0 && pos.y >= 0 && pos.x < rules.width && pos.y < rules.height;
+        let portal = rules.portals.iter().any(|(a, b)| a == pos || b == pos);
+
+        if !on_board || portal || rules.walls.contains(pos) {
+            return Err(format!("food can't go at {},{}", pos.x, pos.y));
+        }
+    }
+
+    let still = DirectionPair(Null, Null);
+
+    Ok(Level {
+        name: name.to_string(),
+        rules: Rules {
+            snake: frame.snake.iter().map(|(pos, _)| (*pos, still)).collect(),
+            food,
+            move_limit,
+            ..frame.rules
+        },
+    })
+}
+
+// named after the file, same as the text levels
+pub fn read_puzzle(path: &Path) -> Result<Level, String> {
+    let s = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace('_', " "))
+        .unwrap_or_default();
+
+    parse_puzzle(&name, &s)
+}
+
+// alphabetical by file name. broken files are skipped with a warning, same as levels
+fn load_puzzles() -> Puzzles {
+    let dir = FileAssetIo::get_base_path().join(PUZZLE_DIR);
+
+    let mut paths = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+
+    paths.sort();
+
+    let list = paths
+        .iter()
+        .filter_map(|path| match read_puzzle(path) {
+            Ok(puzzle) => Some(puzzle),
+            Err(e) => {
+                warn!("skipping puzzle {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+
+    Puzzles { list, current: 0 }
+}
+
+// where the snake is and how far through the food it's got
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct SolverState {
+    // head first
+    body: VecDeque<(i32, i32)>,
+    eaten: usize,
+    // the next food waits for the snake to get off its cell before it's put down
+    placed: bool,
+}
+
+/// the fewest moves that eat all of a puzzle's food, by a breadth first search over
+/// where the snake can be. it plays by the puzzle mode's rules: any bump is fatal, the tail
+/// included, and food only goes down once its cell is free. errors if it can't be done,
+/// can't be done inside the move limit, or there are too many positions to look through
+pub fn solve(rules: &Rules) -> Result<Vec<SnakeDirection>, String> {
+    let free = |pos: Position| {
+        pos.x >= 0
+            && pos.y >= 0
+            && pos.x < rules.width
+            && pos.y < rules.height
+            && !rules.walls.contains(&pos)
+            && !rules.portals.iter().any(|(a, b)| *a == pos || *b == pos)
+    };
+    let food = |i: usize| rules.food.get(i).map(|pos| (pos.x, pos.y));
+
+    if rules.snake.is_empty() || rules.food.is_empty() {
+        return Err("there's nothing to solve without a snake and food".to_string());
+    }
+
+    let body = rules
+        .snake
+        .iter()
+        .map(|(pos, _)| (pos.x, pos.y))
+        .collect::<VecDeque<_>>();
+
+    let start = SolverState {
+        placed: !body.iter().any(|cell| Some(*cell) == food(0)),
+        body,
+        eaten: 0,
+    };
+
+    // the queue is the list of everything seen so far, in the order it was found,
+    // and each one remembers how it was got to
+    let mut states = vec![start.clone()];
+    let mut from: Vec<Option<(usize, SnakeDirection)>> = vec![None];
+    let mut depth = vec![0];
+    let mut seen = HashSet::from([start]);
+    let mut next = 0;
+
+    while next < states.len() {
+        if states.len() > SOLVER_LIMIT {
+            return Err(format!("gave up after {} positions", SOLVER_LIMIT));
+        }
+
+        let state = states[next].clone();
+        let moves = depth[next] + 1;
+
+        if rules.move_limit.is_some_and(|limit| moves > limit) {
+            break;
+        }
+
+        for dir in [Up, Down, Left, Right] {
+            let (x, y) = state.body[0];
+            let to = rules.step(Position { x, y }, dir);
+
+            if !free(to) || state.body.contains(&(to.x, to.y)) {
+                continue;
+            }
+
+            let eats = state.placed && food(state.eaten) == Some((to.x, to.y));
+            let mut body = state.body.clone();
+
+            if !eats {
+                body.pop_back();
+            }
+
+            body.push_front((to.x, to.y));
+
+            let eaten = state.eaten + ifelse!(eats, 1, 0);
+            let placed = ifelse!(eats, false, state.placed)
+                || !body.iter().any(|cell| Some(*cell) == food(eaten));
+
+            if eaten == rules.food.len() {
+                let mut path = vec![dir];
+                let mut at = next;
+
+                while let Some((prev, dir)) = from[at] {
+                    path.push(dir);
+                    at = prev;
+                }
+
+                path.reverse();
+                return Ok(path);
+            }
+
+            let after = SolverState {
+                body,
+                eaten,
+                placed,
+            };
+
+            if seen.insert(after.clone()) {
+                states.push(after);
+                from.push(Some((next, dir)));
+                depth.push(moves);
+            }
+        }
+
+        next += 1;
+    }
+
+    match rules.move_limit {
+        Some(limit) => Err(format!("can't be done in {} moves", limit)),
+        None => Err("can't be done".to_string()),
+    }
+}
+
+// a new game in the puzzle mode is the picked puzzle, if there is one.
+// otherwise it's the mode's fallback, which is the open field
+fn start_puzzle(
+    mut reader: EventReader<NewGameEvent>,
+    config: Res<Config>,
+    puzzles: Res<Puzzles>,
+    mut next_round: ResMut<NextRound>,
+) {
+    if reader.iter().last().is_none() || config.mode != GameMode::Puzzle || next_round.0.is_some() {
+        return;
+    }
+
+    match puzzles.current() {
+        Some(puzzle) => {
+            next_round.0 = Some(Round {
+                mode: GameMode::Puzzle,
+                rules: puzzle.rules.clone(),
+                puzzle: puzzle.name.clone(),
+                ..default()
+            })
+        }
+        None => warn!("there are no puzzles in {}, playing the open field instead", PUZZLE_DIR),
+    }
+}
+
+fn puzzles_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut puzzles: ResMut<Puzzles>,
+    mut config: ResMut<Config>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        let step = match event {
+            MenuEvent::Adjust("puzzle", step) => *step,
+            MenuEvent::Activate("puzzle") => 1,
+            _ => continue,
+        };
+
+        let count = puzzles.list.len() as i32;
+
+        if count > 0 {
+            puzzles.current = (puzzles.current as i32 + step).rem_euclid(count) as usize;
+            config.puzzle = puzzles.list[puzzles.current].name.clone();
+        }
+    }
+
+    if menu.open {
+        let label = match puzzles.current() {
+            Some(Level { name, rules }) => match rules.move_limit {
+                Some(limit) => locale.format(
+                    "menu.puzzle_limited",
+                    &[("name", name.clone()), ("moves", limit.to_string())],
+                ),
+                None => locale.format("menu.puzzle", &[("name", name.clone())]),
+            },
+            None => locale.get("menu.no_puzzle"),
+        };
+
+        entries.set(MenuPage::Main, "puzzle", 26, label);
+    }
+}
+
+pub struct PuzzlePlugin;
+
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own
+        if !app.world.contains_resource::<Puzzles>() {
+            let mut puzzles = load_puzzles();
+
+            if let Some(mut config) = app.world.get_resource_mut::<Config>() {
+                match puzzles.list.iter().position(|p| p.name == config.puzzle) {
+                    Some(i) => puzzles.current = i,
+                    // the menu's best is for whichever puzzle's picked, so there always is one
+                    None => {
+                        if let Some(first) = puzzles.list.first() {
+                            config.puzzle = first.name.clone();
+                        }
+                    }
+                }
+            }
+
+            app.insert_resource(puzzles);
+        }
+
+        app.add_system(puzzles_menu)
+            .add_system(start_puzzle.after(modes_menu).before(new_game));
+    }
+}
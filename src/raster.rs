@@ -0,0 +1,264 @@
+use bevy::{asset::FileAssetIo, ecs::system::SystemParam, prelude::*};
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    creatures::Creature,
+    modes::{Round, Rules},
+    portals::pair_color,
+    theme::{SnakePart, Theme},
+    DirectionPair, Food, FoodKind, Position, Score, SnakeDirection, SnakeSegment, SnakeSegments,
+    SNAKE_SIZE,
+};
+
+// everything on the board at one moment, pulled out of the world so it can be drawn
+// without a window or a gpu. a recording is one of these per tick
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame {
+    // the walls and portals, and how big the board is
+    pub rules: Rules,
+    // head first, same as SnakeSegments
+    pub snake: Vec<(Position, DirectionPair)>,
+    pub food: Vec<(Position, FoodKind)>,
+    pub creatures: Vec<(Position, Creature)>,
+    pub score: u128,
+}
+
+#[derive(SystemParam)]
+pub(crate) struct BoardFrame<'w, 's> {
+    round: Res<'w, Round>,
+    score: Res<'w, Score>,
+    segments: Res<'w, SnakeSegments>,
+    snake: Query<'w, 's, (&'static Position, &'static DirectionPair), With<SnakeSegment>>,
+    food: Query<'w, 's, (&'static Position, &'static FoodKind), With<Food>>,
+    creatures: Query<'w, 's, (&'static Position, &'static Creature)>,
+}
+
+impl BoardFrame<'_, '_> {
+    pub fn frame(&self) -> Frame {
+        Frame {
+            rules: self.round.rules.clone(),
+            ..self.pieces()
+        }
+    }
+
+    // everything but the rules, which don't change from tick to tick
+    pub fn pieces(&self) -> Frame {
+        Frame {
+            rules: Rules::default(),
+            snake: self
+                .segments
+                .iter()
+                .filter_map(|ent| self.snake.get(*ent).ok())
+                .map(|(pos, dir)| (*pos, *dir))
+                .collect(),
+            food: self.food.iter().map(|(pos, kind)| (*pos, *kind)).collect(),
+            creatures: self
+                .creatures
+                .iter()
+                .map(|(pos, creature)| (*pos, creature.clone()))
+                .collect(),
+            score: self.score.0,
+        }
+    }
+}
+
+// the theme's atlas, straight off the disk rather than through the asset server
+pub fn load_atlas(theme: &Theme) -> Result<RgbaImage, String> {
+    let path = FileAssetIo::get_base_path().join("assets").join(&theme.atlas.image);
+
+    image::open(&path)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))
+}
+
+// draws onto an image the way the sprites are laid out on screen: one cell per atlas
+// tile times the scale, a cell's border all round for the outline, and y going up
+struct Canvas<'a> {
+    image: RgbaImage,
+    theme: &'a Theme,
+    atlas: &'a RgbaImage,
+    // pixels per cell
+    cell: f32,
+    height: i32,
+}
+
+impl Canvas<'_> {
+    // pixel middle of a cell
+    fn centre(&self, pos: Position) -> (f32, f32) {
+        (
+            (pos.x as f32 + 1.5) * self.cell,
+            ((self.height - pos.y) as f32 + 0.5) * self.cell,
+        )
+    }
+
+    // the pixels a w by h cells box in the middle of pos covers, clipped to the image
+    fn area(&self, pos: Position, w: f32, h: f32) -> (i64, i64, i64, i64) {
+        let (cx, cy) = self.centre(pos);
+        let (w, h) = (w * self.cell, h * self.cell);
+
+        (
+            (cx - w / 2.0).round() as i64,
+            (cy - h / 2.0).round() as i64,
+            (cx + w / 2.0).round() as i64,
+            (cy + h / 2.0).round() as i64,
+        )
+    }
+
+    // plain alpha blending over what's there. the background's opaque, so everything stays that way
+    fn blend(&mut self, x: i64, y: i64, color: [f32; 4]) {
+        if x < 0 || y < 0 || x >= self.image.width() as i64 || y >= self.image.height() as i64 {
+            return;
+        }
+
+        let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+        let alpha = color[3].clamp(0.0, 1.0);
+
+        for (channel, over) in pixel.0.iter_mut().zip(color).take(3) {
+            let under = *channel as f32 / 255.0;
+            *channel = ((over * alpha + under * (1.0 - alpha)) * 255.0).round() as u8;
+        }
+    }
+
+    fn rect(&mut self, pos: Position, w: f32, h: f32, color: Color) {
+        let (x0, y0, x1, y1) = self.area(pos, w, h);
+        let color = color.as_rgba_f32();
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend(x, y, color);
+            }
+        }
+    }
+
+    // an atlas tile stretched over the box, nearest neighbour, multiplied by the tint
+    // like a TextureAtlasSprite's colour
+    fn tile(&mut self, pos: Position, size: f32, index: usize, tint: Color) {
+        let layout = &self.theme.atlas;
+        let columns = layout.columns.max(1);
+        let (tile_w, tile_h) = (layout.tile_size.0 as u32, layout.tile_size.1 as u32);
+        let left = layout.offset.0 as u32 + (index % columns) as u32 * (tile_w + layout.padding.0 as u32);
+        let top = layout.offset.1 as u32 + (index / columns) as u32 * (tile_h + layout.padding.1 as u32);
+
+        let (x0, y0, x1, y1) = self.area(pos, size, size);
+        let tint = tint.as_rgba_f32();
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let u = left + ((x - x0) as u32 * tile_w / (x1 - x0).max(1) as u32);
+                let v = top + ((y - y0) as u32 * tile_h / (y1 - y0).max(1) as u32);
+
+                // a theme that points past the edge of its image just doesn't get drawn there
+                let texel = match self.atlas.get_pixel_checked(u, v) {
+                    Some(Rgba(texel)) => texel,
+                    None => continue,
+                };
+
+                let mut color = tint;
+                for (channel, texel) in color.iter_mut().zip(texel) {
+                    *channel *= *texel as f32 / 255.0;
+                }
+
+                self.blend(x, y, color);
+            }
+        }
+    }
+}
+
+fn food_color(theme: &Theme, kind: FoodKind, tiled: bool) -> Color {
+    match kind {
+        FoodKind::Normal | FoodKind::Custom { .. } => ifelse!(tiled, Color::WHITE, theme.palette.food),
+        FoodKind::Bonus => theme.palette.bonus_food,
+        FoodKind::Mouse => theme.palette.mouse,
+    }
+}
+
+// one frame, as close to the screen as plain rectangles and atlas tiles get.
+// the score and text aren't drawn, there's no font to draw them with
+pub fn render(frame: &Frame, theme: &Theme, atlas: &RgbaImage, scale: u32) -> RgbaImage {
+    let rules = &frame.rules;
+    let cell = theme.atlas.tile_size.0.max(1.0) * scale.max(1) as f32;
+    let [r, g, b, _] = theme.palette.background.as_rgba_f32();
+    // the background has to stay opaque for the blending to work out
+    let background = Rgba([r, g, b, 1.0].map(|c| (c * 255.0).round() as u8));
+
+    let mut canvas = Canvas {
+        image: RgbaImage::from_pixel(
+            ((rules.width + 2) as f32 * cell) as u32,
+            ((rules.height + 2) as f32 * cell) as u32,
+            background,
+        ),
+        theme,
+        atlas,
+        cell,
+        height: rules.height,
+    };
+
+    let palette = &theme.palette;
+
+    // same half cell bars as draw_outline
+    for y in 0..rules.height {
+        canvas.rect(Position { x: -1, y }, 0.5, 1.0, palette.outline);
+        canvas.rect(Position { x: rules.width, y }, 0.5, 1.0, palette.outline);
+    }
+
+    for x in 0..rules.width {
+        canvas.rect(Position { x, y: -1 }, 1.0, 0.5, palette.outline);
+        canvas.rect(Position { x, y: rules.height }, 1.0, 0.5, palette.outline);
+    }
+
+    for wall in rules.walls.iter() {
+        canvas.rect(*wall, 1.0, 1.0, palette.outline);
+    }
+
+    for (i, (a, b)) in rules.portals.iter().enumerate() {
+        for pos in [a, b] {
+            canvas.rect(*pos, 0.8, 0.8, pair_color(theme, i));
+        }
+    }
+
+    for (pos, kind) in frame.food.iter() {
+        match theme.tiles.food {
+            Some(index) => canvas.tile(*pos, 1.0, index, food_color(theme, *kind, true)),
+            None => canvas.rect(*pos, 1.0, 1.0, food_color(theme, *kind, false)),
+        }
+    }
+
+    for (pos, creature) in frame.creatures.iter() {
+        match creature {
+            Creature::Patrol { .. } => canvas.rect(*pos, 1.0, 1.0, palette.outline),
+            Creature::Rival { body, .. } => {
+                for segment in body.iter().skip(1) {
+                    canvas.rect(*segment, SNAKE_SIZE * 0.8, SNAKE_SIZE * 0.8, palette.rival);
+                }
+
+                canvas.rect(*pos, SNAKE_SIZE, SNAKE_SIZE, palette.rival);
+            }
+        }
+    }
+
+    // tail first, so the head ends up on top like its sprite does
+    let len = frame.snake.len();
+
+    for (i, (pos, dir)) in frame.snake.iter().enumerate().rev() {
+        let part = match i {
+            0 => SnakePart::Head,
+            _ if i == len - 1 => SnakePart::Tail,
+            _ => SnakePart::Body,
+        };
+
+        let index = match part {
+            // mouth open with food right in front, same as animate_head
+            SnakePart::Head
+                if dir.1 != SnakeDirection::Null
+                    && frame.food.iter().any(|(food, _)| *food == rules.step(*pos, dir.1)) =>
+            {
+                theme.tiles.eating.get(dir.1)
+            }
+            _ => theme.tiles.part(part, *dir),
+        };
+
+        canvas.tile(*pos, SNAKE_SIZE, index, palette.tint(part));
+    }
+
+    canvas.image
+}
@@ -0,0 +1,486 @@
+use bevy::{asset::AssetPlugin, asset::FileAssetIo, prelude::*};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    config::{load_config, Config},
+    creatures::CreaturePlugin,
+    layout::LayoutPlugin,
+    levels::Levels,
+    locale::Locale,
+    menu::MenuPlugin,
+    modes::{
+        new_game, GameMode, HighScores, ModePlugin, NewGameEvent, NextRound, Replay, Round, Rules,
+    },
+    portals::PortalPlugin,
+    raster::{load_atlas, render, BoardFrame, Frame},
+    save::{resume_game, ResumeEvent},
+    scripting::{ScriptPlugin, Scripts},
+    stats::Stats,
+    theme::{saved_theme, Theme},
+    tick::{ManualTicks, TickPlugin, TickStage, TickStageLabel},
+    GameOver, GameplayPlugin, SetupPlugin, SnakeDirection,
+};
+
+// press it once the game's over to write the game out
+const EXPORT_KEY: KeyCode = KeyCode::F8;
+const RECORDINGS_DIR: &str = "recordings";
+
+// 1 is the best the gif encoder's colour picking gets and the slowest by far.
+// there aren't many colours on the board anyway
+const GIF_SPEED: i32 = 10;
+
+// how long a playback keeps going after the last turn, for games that don't
+// end by themselves (zen, mostly)
+const REPLAY_TAIL_TICKS: u64 = 500;
+
+pub const USAGE: &str =
+    "usage: snek [--export <game.ron>] [--out <path>] [--scale <n>] [--fps <n>] [--frames]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordFormat {
+    #[default]
+    Gif,
+    // one png per tick, in a folder
+    Png,
+}
+
+/// how exported games come out. set from the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordSettings {
+    /// the gif, or the folder for the pngs. None picks a name under recordings/
+    pub out: Option<PathBuf>,
+    /// pixels per atlas pixel
+    pub scale: u32,
+    /// gif frames per second, one frame per tick
+    pub fps: u32,
+    pub format: RecordFormat,
+}
+
+impl Default for RecordSettings {
+    fn default() -> Self {
+        RecordSettings {
+            out: None,
+            scale: 2,
+            // the speed the snake starts at
+            fps: 8,
+            format: RecordFormat::Gif,
+        }
+    }
+}
+
+/// what main was asked to do
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordArgs {
+    pub settings: RecordSettings,
+    /// a recorded game to turn into a gif without opening a window
+    pub export: Option<PathBuf>,
+}
+
+/// reads the arguments after the program name
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<RecordArgs, String> {
+    let mut parsed = RecordArgs::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+
+        let number = |name: &str, s: String| {
+            s.parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("{} wants a whole number above 0, not {}", name, s))
+        };
+
+        match arg.as_str() {
+            "--export" => parsed.export = Some(PathBuf::from(value("--export")?)),
+            "--out" => parsed.settings.out = Some(PathBuf::from(value("--out")?)),
+            "--scale" => parsed.settings.scale = number("--scale", value("--scale")?)?,
+            "--fps" => parsed.settings.fps = number("--fps", value("--fps")?)?,
+            "--frames" => parsed.settings.format = RecordFormat::Png,
+            _ => return Err(format!("don't know what {} is", arg)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+// enough to play a game again from the top: the board it started on and every turn
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedGame {
+    pub round: Round,
+    pub replay: Replay,
+    // the game speed setting, and the tick it was set from. it changes how long a tick
+    // lasts, and so the clock. older recordings don't have it, they were all at 100
+    #[serde(default)]
+    pub speeds: Vec<(u64, u32)>,
+}
+
+// a frame per tick of the game being played, indexed by tick
+#[derive(Default)]
+pub struct Recorder {
+    // how the game started. a resumed save didn't start here, so it has no replay to write
+    start: Option<Round>,
+    // the board's rules, kept once rather than in every frame. there's another one
+    // whenever something changes them partway through, like a mod putting walls down
+    boards: Vec<Rules>,
+    // which board each frame's on, and the frame with its rules left empty
+    frames: Vec<(usize, Frame)>,
+    // see RecordedGame
+    speeds: Vec<(u64, u32)>,
+}
+
+impl Recorder {
+    // what gets written next to the gif. a resumed save has nothing to start from
+    pub fn game(&self, replay: &Replay) -> Option<RecordedGame> {
+        self.start.as_ref().map(|start| RecordedGame {
+            round: start.clone(),
+            replay: replay.clone(),
+            speeds: self.speeds.clone(),
+        })
+    }
+
+    // the frames with their boards put back, as they're drawn
+    pub fn frames(&self) -> impl Iterator<Item = Frame> + '_ {
+        self.frames.iter().map(|(board, frame)| Frame {
+            rules: self.boards[*board].clone(),
+            ..frame.clone()
+        })
+    }
+}
+
+// draws every frame out to a gif or a folder of pngs
+pub fn write_frames(
+    frames: impl IntoIterator<Item = Frame>,
+    theme: &Theme,
+    settings: &RecordSettings,
+    out: &Path,
+) -> Result<(), String> {
+    let atlas = load_atlas(theme)?;
+    let failed = |e: &dyn std::fmt::Display| format!("couldn't write {}: {}", out.display(), e);
+
+    match settings.format {
+        RecordFormat::Gif => {
+            if let Some(dir) = out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir).map_err(|e| failed(&e))?;
+            }
+
+            let file = File::create(out).map_err(|e| failed(&e))?;
+            let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), GIF_SPEED);
+            let delay = Delay::from_numer_denom_ms(1000, settings.fps.max(1));
+
+            encoder.set_repeat(Repeat::Infinite).map_err(|e| failed(&e))?;
+
+            // drawn as they go in, there's no need for them all at once
+            for frame in frames {
+                let image = render(&frame, theme, &atlas, settings.scale);
+
+                encoder
+                    .encode_frame(image::Frame::from_parts(image, 0, 0, delay))
+                    .map_err(|e| failed(&e))?;
+            }
+        }
+        RecordFormat::Png => {
+            fs::create_dir_all(out).map_err(|e| failed(&e))?;
+
+            for (i, frame) in frames.into_iter().enumerate() {
+                render(&frame, theme, &atlas, settings.scale)
+                    .save(out.join(format!("frame-{:05}.png", i)))
+                    .map_err(|e| failed(&e))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_game(path: &Path, game: &RecordedGame) -> Result<(), String> {
+    ron::ser::to_string_pretty(game, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|s| fs::write(path, s).map_err(|e| e.to_string()))
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
+
+pub fn read_game(path: &Path) -> Result<RecordedGame, String> {
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| ron::from_str(&s).map_err(|e| e.to_string()))
+        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))
+}
+
+fn key_for(dir: SnakeDirection) -> Option<KeyCode> {
+    match dir {
+        SnakeDirection::Up => Some(KeyCode::Up),
+        SnakeDirection::Down => Some(KeyCode::Down),
+        SnakeDirection::Left => Some(KeyCode::Left),
+        SnakeDirection::Right => Some(KeyCode::Right),
+        SnakeDirection::Null => None,
+    }
+}
+
+// plays a recorded game again with nothing on screen, a tick per update, pressing
+// the keys on the ticks the replay says. mods aren't loaded, so a game that had
+// some only plays back the same if they didn't change anything
+pub fn replay_frames(game: &RecordedGame) -> Vec<Frame> {
+    let mut app = App::new();
+
+    app.add_plugins(MinimalPlugins)
+        // the banner and score text still ask for their fonts
+        .add_plugin(AssetPlugin)
+        .insert_resource(Config {
+            mode: game.round.mode,
+            ..default()
+        })
+        .insert_resource(HighScores::default())
+        .insert_resource(Scripts::default())
+        .insert_resource(Levels::default())
+        .insert_resource(Theme::default())
+        .insert_resource(Locale::default())
+        .insert_resource(Input::<KeyCode>::default())
+        .insert_resource(ManualTicks::default())
+        // there's no saving here, but the recorder still listens for resumes
+        .add_event::<ResumeEvent>()
+        .add_plugin(MenuPlugin)
+        .add_plugin(SetupPlugin)
+        .add_plugin(LayoutPlugin)
+        .add_plugin(TickPlugin)
+        .add_plugin(GameplayPlugin)
+        .add_plugin(ModePlugin)
+        .add_plugin(CreaturePlugin)
+        .add_plugin(PortalPlugin)
+        .add_plugin(ScriptPlugin)
+        .add_plugin(RecordPlugin);
+
+    // startup, where the first game gets going, on the recorded board
+    app.insert_resource(NextRound(Some(game.round.clone())));
+    app.update();
+
+    let turns = &game.replay.turns;
+    let last = turns.last().map_or(0, |(tick, _)| *tick);
+    let mut next = 0;
+    let mut next_speed = 0;
+
+    for _ in 0..=last + REPLAY_TAIL_TICKS {
+        if app.world.resource::<GameOver>().0.is_some() {
+            break;
+        }
+
+        // the snake moves on the tick after this one, whether or not it's going yet
+        let upcoming = app.world.resource::<Round>().ticks + 1;
+        let mut key = None;
+
+        while next < turns.len() && turns[next].0 <= upcoming {
+            key = key_for(turns[next].1);
+            next += 1;
+        }
+
+        // the speed setting's put back on the tick it was changed for
+        while next_speed < game.speeds.len() && game.speeds[next_speed].0 <= upcoming {
+            app.world.resource_mut::<Config>().game_speed = game.speeds[next_speed].1;
+            next_speed += 1;
+        }
+
+        // held down until the next turn, like a player would. which way the segments
+        // say they came in depends on it
+        if let Some(key) = key {
+            let mut input = app.world.resource_mut::<Input<KeyCode>>();
+            input.release_all();
+            input.press(key);
+        }
+
+        app.world.resource_mut::<ManualTicks>().0 = 1;
+        app.update();
+    }
+
+    app.world.resource::<Recorder>().frames().collect()
+}
+
+/// turns a recorded game (the .ron next to an exported gif) into a gif or pngs,
+/// without a window. hands back where it went
+pub fn export_replay(path: &Path, settings: &RecordSettings) -> Result<PathBuf, String> {
+    let game = read_game(path)?;
+    let theme = saved_theme(&load_config());
+
+    let out = match &settings.out {
+        Some(out) => out.clone(),
+        None => ifelse!(
+            settings.format == RecordFormat::Gif,
+            path.with_extension("gif"),
+            path.with_extension("")
+        ),
+    };
+
+    write_frames(replay_frames(&game), &theme, settings, &out)?;
+
+    Ok(out)
+}
+
+fn start_recording(
+    mut reader: EventReader<NewGameEvent>,
+    mut recorder: ResMut<Recorder>,
+    round: Res<Round>,
+    config: Res<Config>,
+) {
+    if reader.iter().last().is_some() {
+        *recorder = Recorder {
+            start: Some(round.clone()),
+            speeds: vec![(0, config.game_speed)],
+            ..default()
+        };
+    }
+}
+
+fn stop_on_resume(mut reader: EventReader<ResumeEvent>, mut recorder: ResMut<Recorder>) {
+    if reader.iter().last().is_some() {
+        *recorder = Recorder::default();
+    }
+}
+
+impl Recorder {
+    // the board as it is now goes in as the frame for the round's current tick.
+    // anything past that is from before a rewind, so it goes
+    fn take(&mut self, round: &Round, board: &BoardFrame) {
+        if self.boards.last() != Some(&round.rules) {
+            self.boards.push(round.rules.clone());
+        }
+
+        self.frames.truncate(round.ticks as usize);
+        self.frames.push((self.boards.len() - 1, board.pieces()));
+    }
+}
+
+// once per tick, backwards or forwards, so frame i is always tick i however many
+// ticks a frame catches up on. it runs at the end of the tick, once the new
+// segments and eaten food have landed
+fn record_tick(
+    mut recorder: ResMut<Recorder>,
+    round: Res<Round>,
+    config: Res<Config>,
+    board: BoardFrame,
+) {
+    recorder.take(&round, &board);
+
+    // it's changed in the menu, which holds the ticks, so the tick just gone is the
+    // first one a new speed was used for
+    recorder.speeds.retain(|(tick, _)| *tick <= round.ticks);
+
+    if recorder.speeds.last().is_some_and(|(_, speed)| *speed != config.game_speed) {
+        recorder.speeds.push((round.ticks, config.game_speed));
+    }
+}
+
+// tick 0 is the board before the snake sets off, which a new game puts down
+// outside of any tick
+fn record_start(mut recorder: ResMut<Recorder>, round: Res<Round>, board: BoardFrame) {
+    if round.ticks == 0 {
+        recorder.take(&round, &board);
+    }
+}
+
+fn recording_name(mode: GameMode) -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+
+    format!("snek-{}-{}", mode.name().replace(' ', "-"), secs)
+}
+
+// where an export goes, unless it was given somewhere on the command line
+fn recording_path(round: &Round, settings: &RecordSettings) -> PathBuf {
+    match &settings.out {
+        Some(out) => out.clone(),
+        None => {
+            let path = FileAssetIo::get_base_path()
+                .join(RECORDINGS_DIR)
+                .join(recording_name(round.mode));
+
+            ifelse!(settings.format == RecordFormat::Gif, path.with_extension("gif"), path)
+        }
+    }
+}
+
+// the gif (or pngs), with the game next to it to export again later
+fn export_recording(
+    recorder: &Recorder,
+    replay: &Replay,
+    theme: &Theme,
+    settings: &RecordSettings,
+    out: &Path,
+) -> Result<(), String> {
+    write_frames(recorder.frames(), theme, settings, out)?;
+
+    if let Some(game) = recorder.game(replay) {
+        write_game(&out.with_extension("ron"), &game)?;
+    }
+
+    Ok(())
+}
+
+fn export_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    game_over: Res<GameOver>,
+    recorder: Res<Recorder>,
+    replay: Res<Replay>,
+    round: Res<Round>,
+    theme: Res<Theme>,
+    settings: Res<RecordSettings>,
+    stats: Option<ResMut<Stats>>,
+    locale: Res<Locale>,
+) {
+    if !keyboard_input.just_pressed(EXPORT_KEY) || game_over.0.is_none() {
+        return;
+    }
+
+    let out = recording_path(&round, &settings);
+    let path = [("path", out.display().to_string())];
+
+    // the why is english and for the log, the toast just says it didn't work
+    let message = match export_recording(&recorder, &replay, &theme, &settings, &out) {
+        Ok(()) => locale.format("common.saved", &path),
+        Err(e) => {
+            warn!("{}", e);
+            locale.format("common.save_failed", &path)
+        }
+    };
+
+    // the stats toasts are the one place there is to say things
+    if let Some(mut stats) = stats {
+        stats.toasts.push_back(message);
+    }
+}
+
+pub struct RecordPlugin;
+
+impl Plugin for RecordPlugin {
+    fn build(&self, app: &mut App) {
+        // main brings its own from the command line
+        if !app.world.contains_resource::<RecordSettings>() {
+            app.insert_resource(RecordSettings::default());
+        }
+
+        app.insert_resource(Recorder::default())
+            .add_system(start_recording.after(new_game))
+            .add_system(stop_on_resume.after(resume_game))
+            .add_system(export_controls)
+            .add_system_to_stage(CoreStage::Last, record_start)
+            .stage(TickStageLabel, |stage: &mut TickStage| {
+                stage
+                    .add_system_set(
+                        SystemSet::new().with_system(record_tick.exclusive_system().at_end()),
+                    )
+                    .add_rewind_system_set(
+                        SystemSet::new().with_system(record_tick.exclusive_system().at_end()),
+                    )
+            });
+    }
+}
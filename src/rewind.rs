@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{
+    config::Config,
+    creatures::{despawn_creature, spawn_creature, Creature, RivalSprites},
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    modes::{advance_round, NewGameEvent, Replay, Round},
+    occupancy::{Cell, OccupancyGrid},
+    rng::GameRng,
+    spawn_food_piece, spawn_head, spawn_segment,
+    theme::Theme,
+    tick::{TickClock, TickStage, TickStageLabel},
+    Banner, DirectionPair, Food, FoodKind, GameOver, Position, Score, SnakeDirection,
+    SnakeSegment, SnakeSegments, SpriteSheet,
+};
+
+// hold it to go back one tick at a time, at the speed the game was going
+const REWIND_KEY: KeyCode = KeyCode::Back;
+
+// how much one press moves the setting in the menu
+const REWIND_STEP: usize = 50;
+const MAX_REWIND_TICKS: usize = 1000;
+
+// everything a tick can change, from just before it ran
+struct Snapshot {
+    // head first, same as SnakeSegments
+    snake: Vec<(Position, DirectionPair)>,
+    food: Vec<(Position, FoodKind)>,
+    creatures: Vec<(Position, Creature)>,
+    score: u128,
+    rng: GameRng,
+    ticks: u64,
+    elapsed: Duration,
+    step: Duration,
+    // how long the replay was, turns after this get thrown out
+    turns: usize,
+}
+
+// newest snapshot at the back
+#[derive(Default)]
+pub struct Rewind {
+    history: VecDeque<Snapshot>,
+    // the key's held and there's something to go back to.
+    // the tick stage runs the rewind instead of the game while this is set
+    pub active: bool,
+}
+
+impl Rewind {
+    // how many ticks back it could go right now
+    pub fn depth(&self) -> usize {
+        self.history.len()
+    }
+
+    fn trim(&mut self, limit: usize) {
+        while self.history.len() > limit {
+            self.history.pop_front();
+        }
+    }
+}
+
+fn forget_history(mut reader: EventReader<NewGameEvent>, mut rewind: ResMut<Rewind>) {
+    if reader.iter().last().is_some() {
+        *rewind = Rewind::default();
+    }
+}
+
+fn rewind_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    menu: Res<Menu>,
+    config: Res<Config>,
+    round: Res<Round>,
+    mut rewind: ResMut<Rewind>,
+) {
+    // the setting might've just been turned down
+    rewind.trim(round.mode.rewind_limit(config.rewind_ticks));
+
+    rewind.active = keyboard_input.pressed(REWIND_KEY) && !menu.open && rewind.depth() > 0;
+}
+
+// runs first thing every tick, before anything's moved
+fn take_snapshot(
+    mut rewind: ResMut<Rewind>,
+    config: Res<Config>,
+    round: Res<Round>,
+    segments: Res<SnakeSegments>,
+    snake: Query<(&Position, &DirectionPair), With<SnakeSegment>>,
+    food: Query<(&Position, &FoodKind), With<Food>>,
+    creatures: Query<(&Position, &Creature)>,
+    score: Res<Score>,
+    rng: Res<GameRng>,
+    clock: Res<TickClock>,
+    replay: Res<Replay>,
+) {
+    let limit = round.mode.rewind_limit(config.rewind_ticks);
+
+    // nothing's happening while the snake sits still, so nothing worth going back to
+    let moving = segments
+        .front()
+        .and_then(|head| snake.get(*head).ok())
+        .is_some_and(|(_, dir)| dir.1 != SnakeDirection::Null);
+
+    if limit == 0 || !moving {
+        return;
+    }
+
+    rewind.history.push_back(Snapshot {
+        snake: segments
+            .iter()
+            .filter_map(|ent| snake.get(*ent).ok())
+            .map(|(pos, dir)| (*pos, *dir))
+            .collect(),
+        food: food.iter().map(|(pos, kind)| (*pos, *kind)).collect(),
+        creatures: creatures
+            .iter()
+            .map(|(pos, creature)| (*pos, creature.clone()))
+            .collect(),
+        score: score.0,
+        rng: *rng,
+        ticks: round.ticks,
+        elapsed: round.elapsed,
+        step: clock.step,
+        turns: replay.turns.len(),
+    });
+
+    rewind.trim(limit);
+}
+
+// puts the last snapshot back. the snake and food are rebuilt from scratch,
+// which makes this a handy way to poke at sprite problems too
+fn rewind_tick(
+    mut commands: Commands,
+    mut rewind: ResMut<Rewind>,
+    mut segments: ResMut<SnakeSegments>,
+    pieces: Query<(Entity, &Position), Or<(With<SnakeSegment>, With<Food>)>>,
+    creatures: Query<(Entity, &Position, &Creature, Option<&RivalSprites>)>,
+    mut grid: ResMut<OccupancyGrid>,
+    mut score: ResMut<Score>,
+    mut rng: ResMut<GameRng>,
+    mut round: ResMut<Round>,
+    mut clock: ResMut<TickClock>,
+    mut replay: ResMut<Replay>,
+    mut game_over: ResMut<GameOver>,
+    mut banner: Query<&mut Visibility, With<Banner>>,
+    sprite_sheet: Res<SpriteSheet>,
+    theme: Res<Theme>,
+) {
+    let snapshot = match rewind.history.pop_back() {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+
+    for (ent, pos) in pieces.iter() {
+        grid.set(pos.x, pos.y, Cell::Empty);
+        commands.entity(ent).despawn();
+    }
+
+    for (ent, pos, creature, sprites) in creatures.iter() {
+        despawn_creature(&mut commands, &mut grid, ent, *pos, creature, sprites);
+    }
+
+    segments.clear();
+
+    for (i, (pos, dir)) in snapshot.snake.into_iter().enumerate() {
+        grid.set(pos.x, pos.y, Cell::Snake);
+
+        let spawn = ifelse!(i == 0, spawn_head, spawn_segment);
+        segments.push_back(spawn(&mut commands, pos, dir, &sprite_sheet.0, &theme));
+    }
+
+    for (pos, kind) in snapshot.food {
+        grid.set(pos.x, pos.y, Cell::Food);
+        spawn_food_piece(&mut commands, pos, kind, &sprite_sheet, &theme);
+    }
+
+    for (pos, creature) in snapshot.creatures {
+        spawn_creature(&mut commands, &mut grid, pos, creature, &theme);
+    }
+
+    score.0 = snapshot.score;
+    *rng = snapshot.rng;
+    round.ticks = snapshot.ticks;
+    round.elapsed = snapshot.elapsed;
+    clock.step = snapshot.step;
+    replay.turns.truncate(snapshot.turns);
+
+    // back from the dead
+    if game_over.0.is_some() {
+        game_over.0 = None;
+
+        for mut visibility in &mut banner {
+            visibility.is_visible = false;
+        }
+    }
+
+    if rewind.history.is_empty() {
+        rewind.active = false;
+    }
+}
+
+fn rewind_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut config: ResMut<Config>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        let step = match event {
+            MenuEvent::Adjust("rewind_ticks", n) => *n,
+            MenuEvent::Activate("rewind_ticks") => 1,
+            _ => continue,
+        };
+
+        config.rewind_ticks = (config.rewind_ticks as i64 + step as i64 * REWIND_STEP as i64)
+            .clamp(0, MAX_REWIND_TICKS as i64) as usize;
+    }
+
+    if menu.open {
+        let label = match config.rewind_ticks {
+            0 => locale.get("common.off"),
+            ticks => locale.format("settings.rewind_ticks", &[("ticks", ticks.to_string())]),
+        };
+
+        entries.set(
+            MenuPage::Settings,
+            "rewind_ticks",
+            30,
+            locale.format("settings.rewind", &[("ticks", label)]),
+        );
+    }
+}
+
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Rewind::default())
+            .add_system(rewind_menu)
+            .add_system(forget_history)
+            .add_system(rewind_controls.after(forget_history))
+            .stage(TickStageLabel, |stage: &mut TickStage| {
+                stage
+                    .add_system_set(SystemSet::new().with_system(take_snapshot.before(advance_round)))
+                    .add_rewind_system_set(SystemSet::new().with_system(rewind_tick))
+            });
+    }
+}
@@ -0,0 +1,324 @@
+use bevy::{app::AppExit, asset::FileAssetIo, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    config::Config,
+    creatures::{spawn_creature, Creature, RivalBody},
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    modes::{build_board, Replay, Round},
+    occupancy::{Cell, OccupancyGrid},
+    portals::Portal,
+    rewind::Rewind,
+    rng::GameRng,
+    spawn_food_piece, spawn_head, spawn_segment,
+    theme::Theme,
+    tick::TickClock,
+    DirectionPair, Food, FoodKind, GameOver, Obstacle, Position, Score, SnakeSegment, SnakeSegments,
+    SpriteSheet,
+};
+
+const SAVE_FILE: &str = "save.ron";
+
+// bump this whenever SaveGame changes shape, old saves get turned away with a message
+// instead of loading half right
+pub const SAVE_VERSION: u32 = 2;
+
+// everything needed to pick a game back up where it was left
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveGame {
+    pub version: u32,
+    pub config: Config,
+    pub round: Round,
+    // head first, same as SnakeSegments
+    pub snake: Vec<(Position, DirectionPair)>,
+    pub food: Vec<(Position, FoodKind)>,
+    pub creatures: Vec<(Position, Creature)>,
+    pub score: u128,
+    // how fast the snake was going
+    pub step: Duration,
+    pub rng: GameRng,
+    pub replay: Replay,
+}
+
+// read before the rest, so a save from another version fails on the version
+// and not on whatever field happened to change
+#[derive(Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveError {
+    Io(String),
+    Format(String),
+    Version { found: u32, expected: u32 },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "couldn't get at the save file: {}", e),
+            SaveError::Format(e) => write!(f, "the save file is broken: {}", e),
+            SaveError::Version { found, expected } => write!(
+                f,
+                "the save file is version {}, this build only reads version {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl SaveError {
+    // the display is for the logs, this is what the menu shows
+    pub fn message(&self, locale: &Locale) -> String {
+        match self {
+            SaveError::Io(e) => locale.format("save.io", &[("error", e.clone())]),
+            SaveError::Format(e) => locale.format("save.format", &[("error", e.clone())]),
+            SaveError::Version { found, expected } => locale.format(
+                "save.version",
+                &[("found", found.to_string()), ("expected", expected.to_string())],
+            ),
+        }
+    }
+}
+
+pub fn write_save(path: &Path, save: &SaveGame) -> Result<(), SaveError> {
+    let s = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())
+        .map_err(|e| SaveError::Format(e.to_string()))?;
+
+    fs::write(path, s).map_err(|e| SaveError::Io(e.to_string()))
+}
+
+pub fn read_save(path: &Path) -> Result<SaveGame, SaveError> {
+    let s = fs::read_to_string(path).map_err(|e| SaveError::Io(e.to_string()))?;
+
+    let SaveVersion { version } =
+        ron::from_str(&s).map_err(|e| SaveError::Format(e.to_string()))?;
+
+    if version != SAVE_VERSION {
+        return Err(SaveError::Version {
+            found: version,
+            expected: SAVE_VERSION,
+        });
+    }
+
+    ron::from_str(&s).map_err(|e| SaveError::Format(e.to_string()))
+}
+
+// where saves go, and how the last save or load went
+#[derive(Default)]
+pub struct SaveFile {
+    // nothing's saved without a path, same as the high scores
+    pub path: Option<PathBuf>,
+    pub status: Option<String>,
+}
+
+// saves the game in progress and quits
+pub struct SaveGameEvent;
+
+// a save that's been read and checked, ready to be put on the board
+pub struct ResumeEvent(pub Box<SaveGame>);
+
+fn save_menu(
+    mut menu: ResMut<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut save_file: ResMut<SaveFile>,
+    mut save_writer: EventWriter<SaveGameEvent>,
+    mut resume_writer: EventWriter<ResumeEvent>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        match event {
+            MenuEvent::Activate("save_game") => save_writer.send(SaveGameEvent),
+            MenuEvent::Activate("load_game") => {
+                let path = match &save_file.path {
+                    Some(path) => path,
+                    None => continue,
+                };
+
+                match read_save(path) {
+                    Ok(save) => {
+                        resume_writer.send(ResumeEvent(Box::new(save)));
+                        save_file.status = None;
+                        menu.open = false;
+                    }
+                    Err(e) => {
+                        warn!("couldn't load {}: {}", path.display(), e);
+                        save_file.status = Some(e.message(&locale));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if menu.open {
+        entries.set(MenuPage::Main, "save_game", 40, locale.get("menu.save_game"));
+        entries.set(
+            MenuPage::Main,
+            "load_game",
+            45,
+            match &save_file.status {
+                Some(status) => locale.format("menu.load_game_status", &[("status", status.clone())]),
+                None => locale.get("menu.load_game"),
+            },
+        );
+    }
+}
+
+fn save_game(
+    mut reader: EventReader<SaveGameEvent>,
+    mut save_file: ResMut<SaveFile>,
+    game_over: Res<GameOver>,
+    config: Res<Config>,
+    round: Res<Round>,
+    segments: Res<SnakeSegments>,
+    snake: Query<(&Position, &DirectionPair), With<SnakeSegment>>,
+    food: Query<(&Position, &FoodKind), With<Food>>,
+    creatures: Query<(&Position, &Creature)>,
+    score: Res<Score>,
+    clock: Res<TickClock>,
+    rng: Res<GameRng>,
+    replay: Res<Replay>,
+    mut exit_writer: EventWriter<AppExit>,
+    locale: Res<Locale>,
+) {
+    if reader.iter().last().is_none() {
+        return;
+    }
+
+    // a finished game isn't worth coming back to
+    if game_over.0.is_some() {
+        save_file.status = Some(locale.get("save.game_over"));
+        return;
+    }
+
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        config: config.clone(),
+        round: round.clone(),
+        snake: segments
+            .iter()
+            .filter_map(|ent| snake.get(*ent).ok())
+            .map(|(pos, dir)| (*pos, *dir))
+            .collect(),
+        food: food.iter().map(|(pos, kind)| (*pos, *kind)).collect(),
+        creatures: creatures
+            .iter()
+            .map(|(pos, creature)| (*pos, creature.clone()))
+            .collect(),
+        score: score.0,
+        step: clock.step,
+        rng: *rng,
+        replay: replay.clone(),
+    };
+
+    let path = match &save_file.path {
+        Some(path) => path,
+        None => return,
+    };
+
+    match write_save(path, &save) {
+        Ok(()) => exit_writer.send(AppExit),
+        Err(e) => {
+            warn!("couldn't save {}: {}", path.display(), e);
+            save_file.status = Some(e.message(&locale));
+        }
+    }
+}
+
+// throws away whatever's on the board and puts the saved game there instead
+pub fn resume_game(
+    mut commands: Commands,
+    mut reader: EventReader<ResumeEvent>,
+    pieces: Query<
+        Entity,
+        Or<(
+            With<SnakeSegment>,
+            With<Food>,
+            With<Obstacle>,
+            With<Creature>,
+            With<RivalBody>,
+            With<Portal>,
+        )>,
+    >,
+    mut segments: ResMut<SnakeSegments>,
+    mut grid: ResMut<OccupancyGrid>,
+    mut score: ResMut<Score>,
+    mut game_over: ResMut<GameOver>,
+    mut clock: ResMut<TickClock>,
+    mut round: ResMut<Round>,
+    mut rng: ResMut<GameRng>,
+    mut replay: ResMut<Replay>,
+    mut config: ResMut<Config>,
+    mut rewind: ResMut<Rewind>,
+    sprite_sheet: Res<SpriteSheet>,
+    theme: Res<Theme>,
+) {
+    let save = match reader.iter().last() {
+        Some(ResumeEvent(save)) => save,
+        None => return,
+    };
+
+    for ent in pieces.iter() {
+        commands.entity(ent).despawn();
+    }
+
+    *grid = build_board(&mut commands, &save.round.rules, &theme);
+
+    segments.clear();
+
+    for (i, (pos, dir)) in save.snake.iter().enumerate() {
+        grid.set(pos.x, pos.y, Cell::Snake);
+
+        let spawn = ifelse!(i == 0, spawn_head, spawn_segment);
+        segments.push_back(spawn(&mut commands, *pos, *dir, &sprite_sheet.0, &theme));
+    }
+
+    for (pos, kind) in save.food.iter() {
+        grid.set(pos.x, pos.y, Cell::Food);
+        spawn_food_piece(&mut commands, *pos, *kind, &sprite_sheet, &theme);
+    }
+
+    for (pos, creature) in save.creatures.iter() {
+        spawn_creature(&mut commands, &mut grid, *pos, creature.clone(), &theme);
+    }
+
+    *score = Score(save.score);
+    *game_over = GameOver(None);
+    *clock = TickClock::default();
+    clock.step = save.step;
+    *round = save.round.clone();
+    *rng = save.rng;
+    *replay = save.replay.clone();
+    *config = save.config.clone();
+    // there's no going back to before the save
+    *rewind = Rewind::default();
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own
+        if !app.world.contains_resource::<SaveFile>() {
+            app.insert_resource(SaveFile {
+                path: Some(FileAssetIo::get_base_path().join(SAVE_FILE)),
+                status: None,
+            });
+        }
+
+        app.add_event::<SaveGameEvent>()
+            .add_event::<ResumeEvent>()
+            .add_system(save_menu)
+            .add_system(save_game.after(save_menu))
+            .add_system(resume_game.after(save_menu));
+    }
+}
@@ -0,0 +1,127 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use image::RgbaImage;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    locale::Locale,
+    modes::Round,
+    raster::{load_atlas, render, BoardFrame},
+    recording::RecordSettings,
+    stats::Stats,
+    theme::Theme,
+};
+
+const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+const SCREENSHOTS_DIR: &str = "screenshots";
+
+// where screenshots go, and the last one taken
+#[derive(Default)]
+pub struct Screenshots {
+    // nothing's saved without a path, same as the high scores
+    pub dir: Option<PathBuf>,
+    pub last: Option<PathBuf>,
+}
+
+// a png with tEXt chunks, which the image crate can't write
+pub fn write_png(path: &Path, image: &RgbaImage, text: &[(&str, String)]) -> Result<(), String> {
+    let failed = |e: &dyn std::fmt::Display| format!("couldn't write {}: {}", path.display(), e);
+
+    let file = File::create(path).map_err(|e| failed(&e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    for (keyword, value) in text {
+        encoder
+            .add_text_chunk(keyword.to_string(), value.clone())
+            .map_err(|e| failed(&e))?;
+    }
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(image.as_raw()))
+        .map_err(|e| failed(&e))
+}
+
+// down to the millisecond, so holding the key doesn't write over the last one
+fn screenshot_name() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+
+    format!("snek-{}.png", millis)
+}
+
+// bevy can't read the frame back off the gpu yet, so this is the board drawn again
+// by the same software renderer the recordings use. that also means it works headless
+fn take_screenshot(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut screenshots: ResMut<Screenshots>,
+    board: BoardFrame,
+    round: Res<Round>,
+    theme: Res<Theme>,
+    settings: Res<RecordSettings>,
+    stats: Option<ResMut<Stats>>,
+    locale: Res<Locale>,
+) {
+    if !keyboard_input.just_pressed(SCREENSHOT_KEY) {
+        return;
+    }
+
+    let dir = match &screenshots.dir {
+        Some(dir) => dir.clone(),
+        None => return,
+    };
+
+    let frame = board.frame();
+    let path = dir.join(screenshot_name());
+    let text = [
+        ("Title", "snek".to_string()),
+        ("Score", frame.score.to_string()),
+        ("Mode", round.mode.name()),
+    ];
+
+    let result = fs::create_dir_all(&dir)
+        .map_err(|e| format!("couldn't make {}: {}", dir.display(), e))
+        .and_then(|_| load_atlas(&theme))
+        .and_then(|atlas| write_png(&path, &render(&frame, &theme, &atlas, settings.scale), &text));
+
+    let message = match result {
+        Ok(()) => {
+            let message = locale.format("common.saved", &[("path", path.display().to_string())]);
+            screenshots.last = Some(path);
+            message
+        }
+        Err(e) => {
+            warn!("{}", e);
+            locale.format("common.save_failed", &[("path", path.display().to_string())])
+        }
+    };
+
+    if let Some(mut stats) = stats {
+        stats.toasts.push_back(message);
+    }
+}
+
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own
+        if !app.world.contains_resource::<Screenshots>() {
+            app.insert_resource(Screenshots {
+                dir: Some(FileAssetIo::get_base_path().join(SCREENSHOTS_DIR)),
+                last: None,
+            });
+        }
+
+        // after the frame's ticks, so it's the board as it'll be drawn
+        app.add_system_to_stage(CoreStage::Last, take_screenshot);
+    }
+}
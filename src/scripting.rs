@@ -0,0 +1,507 @@
+use bevy::{asset::FileAssetIo, ecs::system::SystemParam, prelude::*};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    draw_bg_element,
+    layout::BoardLayout,
+    locale::Locale,
+    modes::Round,
+    occupancy::{Cell, OccupancyGrid},
+    spawn_food_piece,
+    theme::Theme,
+    tick::{TickStage, TickStageLabel},
+    CollisionEvent, FoodKind, Obstacle, Position, Score, ScoredEvent, SnakeSegment, SnakeSegments,
+    SpriteSheet,
+};
+
+// every *.rhai in here is loaded at startup, in file name order. subfolders aren't,
+// so examples can sit in mods/examples without switching themselves on
+const MODS_DIR: &str = "mods";
+
+const CONSOLE_KEY: KeyCode = KeyCode::Grave;
+const CONSOLE_LINES: usize = 8;
+const CONSOLE_FONT_TILES: f32 = 0.7;
+
+// a hook that loops forever gets stopped instead of hanging the game
+const MAX_OPERATIONS: u64 = 100_000;
+
+// the range a mod's tick length is kept to, in milliseconds
+const MIN_SCRIPT_STEP: f64 = 1.0;
+const MAX_SCRIPT_STEP: f64 = 10_000.0;
+
+// what a script asked for, done by apply_script_commands once the hooks have run
+#[derive(Debug, Clone, PartialEq)]
+enum ScriptCommand {
+    Print(String),
+    Food(Position),
+    Wall(Position),
+}
+
+// one loaded script. the scope keeps whatever its top level set up
+struct Mod {
+    name: String,
+    ast: AST,
+    scope: Scope<'static>,
+}
+
+// the script engine, every mod it's running, and the console they write to
+pub struct Scripts {
+    engine: Engine,
+    mods: Vec<Mod>,
+    // shared with the functions the scripts call
+    queue: Arc<Mutex<Vec<ScriptCommand>>>,
+    // newest at the back. errors end up here instead of taking the game down
+    pub console: VecDeque<String>,
+    pub console_open: bool,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        let queue = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let print = queue.clone();
+        engine.on_print(move |s| {
+            print
+                .lock()
+                .unwrap()
+                .push(ScriptCommand::Print(s.to_string()))
+        });
+
+        let debug = queue.clone();
+        engine.on_debug(move |s, _, _| {
+            debug
+                .lock()
+                .unwrap()
+                .push(ScriptCommand::Print(s.to_string()))
+        });
+
+        let food = queue.clone();
+        engine.register_fn("spawn_food", move |x: i64, y: i64| {
+            food.lock().unwrap().push(ScriptCommand::Food(Position {
+                x: x as i32,
+                y: y as i32,
+            }))
+        });
+
+        let wall = queue.clone();
+        engine.register_fn("spawn_wall", move |x: i64, y: i64| {
+            wall.lock().unwrap().push(ScriptCommand::Wall(Position {
+                x: x as i32,
+                y: y as i32,
+            }))
+        });
+
+        Scripts {
+            engine,
+            mods: Vec::new(),
+            queue,
+            console: VecDeque::new(),
+            console_open: true,
+        }
+    }
+}
+
+impl Scripts {
+    pub fn log(&mut self, line: impl Into<String>) {
+        let line = line.into();
+
+        // a hook failing every tick would push everything else off otherwise
+        if self.console.back() == Some(&line) {
+            return;
+        }
+
+        info!("{}", line);
+        self.console.push_back(line);
+
+        while self.console.len() > CONSOLE_LINES {
+            self.console.pop_front();
+        }
+    }
+
+    // compiles a script and runs its top level once. a broken one is logged and left out
+    pub fn add(&mut self, name: &str, source: &str) {
+        let ast = match self.engine.compile(source) {
+            Ok(ast) => ast,
+            Err(e) => return self.log(format!("{}: {}", name, e)),
+        };
+
+        let mut scope = Scope::new();
+
+        if let Err(e) = self.engine.run_ast_with_scope(&mut scope, &ast) {
+            return self.log(format!("{}: {}", name, e));
+        }
+
+        self.mods.push(Mod {
+            name: name.to_string(),
+            ast,
+            scope,
+        });
+    }
+
+    // any mod with this hook, taking this many arguments
+    fn defines(&self, hook: &str, arity: usize) -> bool {
+        self.mods.iter().any(|m| {
+            m.ast
+                .iter_functions()
+                .any(|f| f.name == hook && f.params.len() == arity)
+        })
+    }
+
+    // calls the hook in every mod that has it, in load order.
+    // the first answer that isn't () is the one that counts
+    fn call(&mut self, hook: &str, args: Vec<Dynamic>) -> Option<Dynamic> {
+        let Scripts {
+            engine,
+            mods,
+            queue,
+            ..
+        } = self;
+
+        let mut answer = None;
+        let mut errors = Vec::new();
+
+        for m in mods.iter_mut() {
+            if !m
+                .ast
+                .iter_functions()
+                .any(|f| f.name == hook && f.params.len() == args.len())
+            {
+                continue;
+            }
+
+            let options = CallFnOptions::new().eval_ast(false);
+
+            match engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut m.scope,
+                &m.ast,
+                hook,
+                args.clone(),
+            ) {
+                Ok(result) if answer.is_none() && !result.is_unit() => answer = Some(result),
+                Ok(_) => (),
+                Err(e) => errors.push(format!("{}: {}: {}", m.name, hook, e)),
+            }
+        }
+
+        queue
+            .lock()
+            .unwrap()
+            .extend(errors.into_iter().map(ScriptCommand::Print));
+
+        answer
+    }
+
+    // where the next food goes, if a mod wants a say. anything off the board or
+    // already taken is ignored and the food lands somewhere random as usual
+    pub fn food_position(
+        &mut self,
+        grid: &OccupancyGrid,
+        board: impl FnOnce() -> Map,
+    ) -> Option<Position> {
+        if !self.defines("spawn_food_position", 1) {
+            return None;
+        }
+
+        let answer = self.call("spawn_food_position", vec![board().into()])?;
+        let pos = answer.try_cast::<Map>().and_then(|map| map_position(&map));
+
+        match pos {
+            Some(pos) if grid.get(pos.x, pos.y) == Cell::Empty => Some(pos),
+            _ => {
+                self.log("spawn_food_position: wanted #{x, y} on a free cell");
+                None
+            }
+        }
+    }
+
+    // how long a tick should take, if a mod wants a say. in milliseconds
+    pub fn speed_for(&mut self, score: u128) -> Option<Duration> {
+        if !self.defines("speed_for", 1) {
+            return None;
+        }
+
+        let answer = self.call("speed_for", vec![(score as i64).into()])?;
+        let ms = answer
+            .as_int()
+            .map(|ms| ms as f64)
+            .or_else(|_| answer.as_float())
+            .ok()
+            .filter(|ms| *ms > 0.0);
+
+        // anything too fast or too slow is kept in range, but the mod hears about it
+        let ms = match ms {
+            Some(ms) if !(MIN_SCRIPT_STEP..=MAX_SCRIPT_STEP).contains(&ms) => {
+                self.log(format!(
+                    "speed_for: out of range, keeping it between {} and {} ms",
+                    MIN_SCRIPT_STEP, MAX_SCRIPT_STEP
+                ));
+                Some(ms.clamp(MIN_SCRIPT_STEP, MAX_SCRIPT_STEP))
+            }
+            ms => ms,
+        };
+
+        match ms.and_then(|ms| Duration::try_from_secs_f64(ms / 1000.0).ok()) {
+            Some(step) => Some(step),
+            None => {
+                self.log("speed_for: wanted a number of milliseconds");
+                None
+            }
+        }
+    }
+}
+
+fn map_position(map: &Map) -> Option<Position> {
+    let get = |key: &str| map.get(key).and_then(|v| v.as_int().ok());
+
+    Some(Position {
+        x: get("x")? as i32,
+        y: get("y")? as i32,
+    })
+}
+
+fn position_map(pos: Position) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("x".into(), (pos.x as i64).into());
+    map.insert("y".into(), (pos.y as i64).into());
+    map.into()
+}
+
+fn food_name(kind: FoodKind) -> &'static str {
+    match kind {
+        FoodKind::Normal => "normal",
+        FoodKind::Bonus => "bonus",
+        FoodKind::Mouse => "mouse",
+        FoodKind::Custom { .. } => "custom",
+    }
+}
+
+// everything the hooks get to see about the game, besides the grid
+#[derive(SystemParam)]
+pub(crate) struct BoardView<'w, 's> {
+    round: Res<'w, Round>,
+    score: Res<'w, Score>,
+    segments: Res<'w, SnakeSegments>,
+    positions: Query<'w, 's, &'static Position, With<SnakeSegment>>,
+}
+
+impl BoardView<'_, '_> {
+    // what a script sees as `board`: its size, the score, the snake head first,
+    // and every cell by name, as board.cells[y][x]
+    pub fn map(&self, grid: &OccupancyGrid) -> Map {
+        let snake = self
+            .segments
+            .iter()
+            .filter_map(|ent| self.positions.get(*ent).ok())
+            .map(|pos| position_map(*pos))
+            .collect::<Vec<Dynamic>>();
+
+        let cells = (0..grid.height())
+            .map(|y| {
+                (0..grid.width())
+                    .map(|x| Dynamic::from(grid.get(x, y).name().to_string()))
+                    .collect::<Vec<Dynamic>>()
+                    .into()
+            })
+            .collect::<Vec<Dynamic>>();
+
+        let mut map = Map::new();
+        map.insert("width".into(), (grid.width() as i64).into());
+        map.insert("height".into(), (grid.height() as i64).into());
+        map.insert("mode".into(), self.round.mode.name().into());
+        map.insert("ticks".into(), (self.round.ticks as i64).into());
+        map.insert("score".into(), (self.score.0 as i64).into());
+        map.insert(
+            "head".into(),
+            snake.first().cloned().unwrap_or(Dynamic::UNIT),
+        );
+        map.insert("snake".into(), snake.into());
+        map.insert("cells".into(), cells.into());
+        map
+    }
+}
+
+// everything in the mods folder, alphabetical
+fn load_scripts(dir: &Path) -> Scripts {
+    let mut scripts = Scripts::default();
+
+    let mut paths = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+
+    paths.sort();
+
+    for path in paths {
+        let name = path
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().to_string());
+
+        match fs::read_to_string(&path) {
+            Ok(source) => scripts.add(&name, &source),
+            Err(e) => scripts.log(format!("{}: {}", name, e)),
+        }
+    }
+
+    if !scripts.mods.is_empty() {
+        let names = scripts
+            .mods
+            .iter()
+            .map(|m| m.name.clone())
+            .collect::<Vec<_>>();
+        scripts.log(format!("mods: {}", names.join(", ")));
+    }
+
+    scripts
+}
+
+// on_collision(cause) and on_eat(food_kind), for whatever happened this tick
+fn script_events(
+    mut scripts: ResMut<Scripts>,
+    grid: Res<OccupancyGrid>,
+    mut collision_reader: EventReader<CollisionEvent>,
+    mut score_reader: EventReader<ScoredEvent>,
+) {
+    for collision in collision_reader.iter() {
+        scripts.call("on_collision", vec![collision.cause(&grid).into()]);
+    }
+
+    for ScoredEvent(_, kind) in score_reader.iter() {
+        scripts.call("on_eat", vec![food_name(*kind).into()]);
+    }
+}
+
+fn script_tick(mut scripts: ResMut<Scripts>, grid: Res<OccupancyGrid>, board: BoardView) {
+    if scripts.defines("on_tick", 1) {
+        scripts.call("on_tick", vec![board.map(&grid).into()]);
+    }
+}
+
+// food and walls only go on empty cells, the grid has the last word same as everywhere else
+fn apply_script_commands(
+    mut commands: Commands,
+    mut scripts: ResMut<Scripts>,
+    mut grid: ResMut<OccupancyGrid>,
+    mut round: ResMut<Round>,
+    sprite_sheet: Res<SpriteSheet>,
+    theme: Res<Theme>,
+) {
+    let queued = std::mem::take(&mut *scripts.queue.lock().unwrap());
+
+    for command in queued {
+        let pos = match command {
+            ScriptCommand::Print(line) => {
+                scripts.log(line);
+                continue;
+            }
+            ScriptCommand::Food(pos) | ScriptCommand::Wall(pos) => pos,
+        };
+
+        if grid.get(pos.x, pos.y) != Cell::Empty {
+            scripts.log(format!("can't put {:?} there, it's not free", command));
+            continue;
+        }
+
+        if let ScriptCommand::Food(_) = command {
+            grid.set(pos.x, pos.y, Cell::Food);
+            spawn_food_piece(&mut commands, pos, FoodKind::Normal, &sprite_sheet, &theme);
+        } else {
+            grid.set(pos.x, pos.y, Cell::Wall);
+            // part of the board from now on, so a save brings it back
+            round.rules.walls.push(pos);
+
+            let ent = draw_bg_element(pos.x, pos.y, 1.0, 1.0, theme.palette.outline, &mut commands);
+            commands.entity(ent).insert(Obstacle);
+        }
+    }
+}
+
+#[derive(Component)]
+struct ConsoleText;
+
+fn setup_console(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: 16.0,
+        color: Color::rgb(1.0, 0.8, 0.3),
+    };
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::from_section("", style).with_alignment(TextAlignment::TOP_LEFT),
+            transform: Transform::from_xyz(0.0, 0.0, 80.0),
+            ..default()
+        })
+        .insert(ConsoleText);
+}
+
+// top left of the window, over the board
+fn draw_console(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut scripts: ResMut<Scripts>,
+    layout: Res<BoardLayout>,
+    mut text: Query<(&mut Text, &mut Transform, &mut Visibility), With<ConsoleText>>,
+) {
+    if keyboard_input.just_pressed(CONSOLE_KEY) {
+        scripts.console_open = !scripts.console_open;
+    }
+
+    for (mut text, mut transform, mut visibility) in &mut text {
+        visibility.is_visible = scripts.console_open && !scripts.console.is_empty();
+
+        let value = scripts
+            .console
+            .iter()
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+
+        text.sections[0].style.font_size = CONSOLE_FONT_TILES * layout.tile_size;
+        transform.translation.x = -layout.window.x / 2.0 + 8.0;
+        transform.translation.y = layout.window.y / 2.0 - 8.0;
+    }
+}
+
+pub struct ScriptPlugin;
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own
+        if !app.world.contains_resource::<Scripts>() {
+            app.insert_resource(load_scripts(&FileAssetIo::get_base_path().join(MODS_DIR)));
+        }
+
+        app.add_startup_system(setup_console)
+            .add_system(draw_console)
+            .stage(TickStageLabel, |stage: &mut TickStage| {
+                stage.add_system_set(
+                    SystemSet::new()
+                        .with_system(script_events.after(crate::GameSystem::Collide))
+                        .with_system(script_tick.after(script_events).after(crate::spawn_food))
+                        .with_system(
+                            apply_script_commands
+                                .after(script_tick)
+                                .before(crate::end_game),
+                        ),
+                )
+            });
+    }
+}
@@ -0,0 +1,434 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs,
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{
+    layout::BoardLayout,
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    modes::{NewGameEvent, Replay, Round},
+    occupancy::{Cell, OccupancyGrid},
+    save::ResumeEvent,
+    tick::{TickStage, TickStageLabel},
+    CollisionEvent, GameOver, GameSystem, Outcome, Score, ScoredEvent, SnakeSegments,
+};
+
+const STATS_FILE: &str = "stats.ron";
+
+// how long an unlock stays up
+const TOAST_SECS: f32 = 3.0;
+const TOAST_FONT_TILES: f32 = 1.0;
+
+// everything that's kept between games, next to the high scores.
+// the bests feed the achievements, so they only ever go up
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Lifetime {
+    pub games_played: u64,
+    pub food_eaten: u64,
+    pub longest_snake: u64,
+    pub longest_survival: Duration,
+    // by what killed the snake, see CollisionEvent::cause
+    pub deaths: BTreeMap<String, u64>,
+    pub turns: u64,
+    pub best_score: u64,
+    // most of the board the snake has covered, in percent
+    pub best_fill: u64,
+    // most food eaten in one go without turning on two ticks running
+    pub best_steady: u64,
+    pub unlocked: BTreeSet<String>,
+}
+
+pub struct Achievement {
+    // also its menu id, and achievement.<id> is its name in the language files
+    pub id: &'static str,
+    pub goal: u64,
+    pub progress: fn(&Lifetime) -> u64,
+}
+
+pub const ACHIEVEMENTS: [Achievement; 6] = [
+    Achievement {
+        id: "first_bite",
+        goal: 1,
+        progress: |l| l.food_eaten,
+    },
+    Achievement {
+        id: "regular",
+        goal: 25,
+        progress: |l| l.games_played,
+    },
+    Achievement {
+        id: "half_full",
+        goal: 50,
+        progress: |l| l.best_fill,
+    },
+    Achievement {
+        id: "steady",
+        goal: 10,
+        progress: |l| l.best_steady,
+    },
+    Achievement {
+        id: "centurion",
+        goal: 100,
+        progress: |l| l.best_score,
+    },
+    Achievement {
+        id: "long_boi",
+        goal: 40,
+        progress: |l| l.longest_snake,
+    },
+];
+
+// the game in progress, as far as the stats care
+#[derive(Default)]
+struct Progress {
+    // how much of the replay's been counted
+    seen_turns: usize,
+    last_turn: Option<u64>,
+    steady: u64,
+    // the last thing the head ran into
+    cause: Option<&'static str>,
+    // how many cells there are to fill, worked out on the round's first tick
+    room: Option<u64>,
+    // the furthest tick that's been counted. a rewind goes back over ticks that
+    // already were, so playing them again doesn't count twice
+    counted: u64,
+}
+
+#[derive(Default)]
+pub struct Stats {
+    pub lifetime: Lifetime,
+    game: Progress,
+    // unlocks waiting to be shown, the front one's up now
+    pub toasts: VecDeque<String>,
+    toast_timer: Option<Timer>,
+    // nothing's saved without a path, same as the high scores
+    path: Option<PathBuf>,
+}
+
+impl Achievement {
+    pub fn name(&self, locale: &Locale) -> String {
+        locale.get(&format!("achievement.{}", self.id))
+    }
+}
+
+impl Stats {
+    // anything that's just reached its goal. true if there was something
+    fn unlock(&mut self, locale: &Locale) -> bool {
+        let mut any = false;
+
+        for achievement in ACHIEVEMENTS.iter() {
+            if (achievement.progress)(&self.lifetime) >= achievement.goal
+                && self.lifetime.unlocked.insert(achievement.id.to_string())
+            {
+                let name = achievement.name(locale);
+                self.toasts.push_back(locale.format("stats.unlocked", &[("name", name)]));
+                any = true;
+            }
+        }
+
+        any
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let result = ron::ser::to_string_pretty(&self.lifetime, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|s| fs::write(path, s).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            warn!("couldn't save {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn load_stats() -> Stats {
+    let path = FileAssetIo::get_base_path().join(STATS_FILE);
+
+    let lifetime = match fs::read_to_string(&path) {
+        Ok(s) => ron::from_str(&s).unwrap_or_else(|e| {
+            warn!("couldn't read {}: {}, starting fresh", path.display(), e);
+            Lifetime::default()
+        }),
+        Err(_) => Lifetime::default(),
+    };
+
+    Stats {
+        lifetime,
+        path: Some(path),
+        ..default()
+    }
+}
+
+fn forget_game(
+    mut reader: EventReader<NewGameEvent>,
+    mut resume_reader: EventReader<ResumeEvent>,
+    mut stats: ResMut<Stats>,
+) {
+    if reader.iter().last().is_some() {
+        stats.game = Progress::default();
+    }
+
+    // a saved game carries on counting, but it can be on another board
+    if resume_reader.iter().last().is_some() {
+        stats.game.room = None;
+    }
+}
+
+// runs every tick, once the snake's moved and eaten
+fn track_stats(
+    mut stats: ResMut<Stats>,
+    mut score_reader: EventReader<ScoredEvent>,
+    mut collision_reader: EventReader<CollisionEvent>,
+    replay: Res<Replay>,
+    round: Res<Round>,
+    segments: Res<SnakeSegments>,
+    grid: Res<OccupancyGrid>,
+    score: Res<Score>,
+    locale: Res<Locale>,
+) {
+    let stats = &mut *stats;
+
+    // a rewind can take turns back off the end
+    stats.game.seen_turns = stats.game.seen_turns.min(replay.turns.len());
+
+    for i in stats.game.seen_turns..replay.turns.len() {
+        let tick = replay.turns[i].0;
+
+        // the first one's just setting off
+        if i > 0 {
+            if tick > stats.game.counted {
+                stats.lifetime.turns += 1;
+            }
+
+            if stats.game.last_turn.is_some_and(|last| last + 1 == tick) {
+                stats.game.steady = 0;
+            }
+        }
+
+        stats.game.last_turn = Some(tick);
+    }
+
+    stats.game.seen_turns = replay.turns.len();
+
+    let fresh = round.ticks > stats.game.counted;
+    stats.game.counted = stats.game.counted.max(round.ticks);
+
+    for _ in score_reader.iter().filter(|_| fresh) {
+        stats.lifetime.food_eaten += 1;
+        stats.game.steady += 1;
+    }
+
+    for collision in collision_reader.iter() {
+        stats.game.cause = Some(collision.cause(&grid));
+    }
+
+    // walls and portals can't be filled, so they don't count against you
+    let room = *stats.game.room.get_or_insert_with(|| {
+        (0..grid.height())
+            .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| !matches!(grid.get(*x, *y), Cell::Wall | Cell::Portal))
+            .count()
+            .max(1) as u64
+    });
+    let length = segments.len() as u64;
+
+    let lifetime = &mut stats.lifetime;
+    lifetime.longest_snake = lifetime.longest_snake.max(length);
+    lifetime.best_fill = lifetime.best_fill.max(length * 100 / room);
+    lifetime.best_score = lifetime.best_score.max(score.0 as u64);
+    lifetime.best_steady = lifetime.best_steady.max(stats.game.steady);
+
+    if stats.unlock(&locale) {
+        stats.save();
+    }
+}
+
+fn finish_game(
+    game_over: Res<GameOver>,
+    round: Res<Round>,
+    mut stats: ResMut<Stats>,
+    locale: Res<Locale>,
+) {
+    let outcome = match game_over.0 {
+        Some(outcome) if game_over.is_changed() => outcome,
+        _ => return,
+    };
+
+    let cause = stats.game.cause.unwrap_or("unknown");
+    let lifetime = &mut stats.lifetime;
+
+    lifetime.games_played += 1;
+    lifetime.longest_survival = lifetime.longest_survival.max(round.elapsed);
+
+    if outcome == Outcome::Died {
+        *lifetime.deaths.entry(cause.to_string()).or_default() += 1;
+    }
+
+    stats.unlock(&locale);
+    stats.save();
+}
+
+#[derive(Component)]
+struct Toast;
+
+fn setup_toast(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: 24.0,
+        color: Color::GOLD,
+    };
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::from_section("", style).with_alignment(TextAlignment::TOP_CENTER),
+            transform: Transform::from_xyz(0.0, 0.0, 85.0),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(Toast);
+}
+
+// one unlock at a time, along the top of the window
+fn show_toasts(
+    time: Res<Time>,
+    layout: Res<BoardLayout>,
+    mut stats: ResMut<Stats>,
+    mut toast: Query<(&mut Text, &mut Transform, &mut Visibility), With<Toast>>,
+) {
+    if let Some(timer) = &mut stats.toast_timer {
+        if timer.tick(time.delta()).finished() {
+            stats.toasts.pop_front();
+            stats.toast_timer = None;
+        }
+    }
+
+    if stats.toast_timer.is_none() && !stats.toasts.is_empty() {
+        stats.toast_timer = Some(Timer::from_seconds(TOAST_SECS, false));
+    }
+
+    for (mut text, mut transform, mut visibility) in &mut toast {
+        visibility.is_visible = !stats.toasts.is_empty();
+
+        if let Some(front) = stats.toasts.front() {
+            if text.sections[0].value != *front {
+                text.sections[0].value = front.clone();
+            }
+        }
+
+        text.sections[0].style.font_size = TOAST_FONT_TILES * layout.tile_size;
+        transform.translation.y = layout.window.y / 2.0 - 8.0;
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn stats_menu(
+    mut menu: ResMut<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    stats: Res<Stats>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        match event {
+            MenuEvent::Activate("stats") => menu.show(MenuPage::Stats),
+            MenuEvent::Activate("stats_back") => menu.show(MenuPage::Main),
+            _ => (),
+        }
+    }
+
+    if !menu.open {
+        return;
+    }
+
+    let lifetime = &stats.lifetime;
+    // the causes are kept in english, they're only translated to be shown
+    let deaths = lifetime
+        .deaths
+        .iter()
+        .map(|(cause, n)| format!("{} {}", locale.get(&format!("cause.{}", cause)), n))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let count = |key: &str, n: u64| locale.format(key, &[("n", n.to_string())]);
+
+    entries.set(MenuPage::Main, "stats", 35, locale.get("menu.stats"));
+
+    let lines = [
+        ("stats_games", count("stats.games", lifetime.games_played)),
+        ("stats_food", count("stats.food", lifetime.food_eaten)),
+        ("stats_longest", count("stats.longest", lifetime.longest_snake)),
+        (
+            "stats_survival",
+            locale.format(
+                "stats.survival",
+                &[("time", format_duration(lifetime.longest_survival))],
+            ),
+        ),
+        ("stats_turns", count("stats.turns", lifetime.turns)),
+        (
+            "stats_deaths",
+            locale.format(
+                "stats.deaths",
+                &[("deaths", ifelse!(deaths.is_empty(), locale.get("common.none"), deaths))],
+            ),
+        ),
+    ];
+
+    for (i, (id, label)) in lines.into_iter().enumerate() {
+        entries.set(MenuPage::Stats, id, i as i32, label);
+    }
+
+    for (i, achievement) in ACHIEVEMENTS.iter().enumerate() {
+        let progress = (achievement.progress)(lifetime).min(achievement.goal);
+        let label = ifelse!(
+            lifetime.unlocked.contains(achievement.id),
+            format!("[x] {}", achievement.name(&locale)),
+            format!(
+                "[ ] {} ({}/{})",
+                achievement.name(&locale),
+                progress,
+                achievement.goal
+            )
+        );
+
+        entries.set(MenuPage::Stats, achievement.id, 100 + i as i32, label);
+    }
+
+    entries.set(MenuPage::Stats, "stats_back", 1000, locale.get("common.back"));
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        // tests bring their own
+        if !app.world.contains_resource::<Stats>() {
+            app.insert_resource(load_stats());
+        }
+
+        app.add_startup_system(setup_toast)
+            .add_system(stats_menu)
+            .add_system(forget_game)
+            .add_system(show_toasts)
+            .add_system_to_stage(CoreStage::Last, finish_game)
+            .stage(TickStageLabel, |stage: &mut TickStage| {
+                stage.add_system_set(
+                    SystemSet::new()
+                        .with_system(track_stats.after(GameSystem::Score).before(crate::end_game)),
+                )
+            });
+    }
+}
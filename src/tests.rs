@@ -0,0 +1,1980 @@
+// runs the real gameplay systems headlessly: no window, no renderer, no audio.
+// keys are pressed straight into Input<KeyCode>, and the snake only moves
+// when a test hands out ManualTicks, so nothing here depends on the clock
+
+use bevy::{asset::AssetPlugin, prelude::*};
+
+use crate::{
+    accessibility::{AccessibilityPlugin, ColorAssist, Decoration},
+    config::Config,
+    creatures::{Creature, CreaturePlugin},
+    daily::{daily_rules, DailyPlugin, DailyResults, Date},
+    hud::{HudItem, HudPlugin, PowerUp, PowerUps},
+    layout::LayoutPlugin,
+    levels::{read_level, Level, LevelPlugin, Levels},
+    locale::{parse_language, Locale, LocalePlugin},
+    menu::{Menu, MenuPlugin},
+    modes::{
+        GameMode, HighScores, ModePlugin, NewGameEvent, Replay, Round, Rules, SURVIVAL_GROW_TICKS,
+    },
+    occupancy::{Cell, OccupancyGrid},
+    menu::MenuEvent,
+    portals::PortalPlugin,
+    puzzles::{parse_puzzle, read_puzzle, solve, PuzzlePlugin, Puzzles},
+    raster::{load_atlas, render, Frame},
+    recording::{
+        parse_args, replay_frames, write_frames, RecordFormat, RecordPlugin, RecordSettings,
+        Recorder,
+    },
+    rewind::{Rewind, RewindPlugin},
+    rng::GameRng,
+    notation::{parse_board, print_board},
+    save::{
+        read_save, write_save, ResumeEvent, SaveError, SaveFile, SaveGame, SaveGameEvent,
+        SavePlugin, SAVE_VERSION,
+    },
+    screenshot::{ScreenshotPlugin, Screenshots},
+    scripting::{ScriptPlugin, Scripts},
+    stats::{Stats, StatsPlugin},
+    theme::{Theme, Themes},
+    tick::{ManualTicks, Tick, TickClock, TickPlugin, MAX_CATCH_UP},
+    DirectionPair, Food, FoodKind, GameOver, GameRenderPlugin, GameSystem, GameplayPlugin, Position,
+    Score, ScoreText, ScoredEvent, SetupPlugin, SnakeDirection, SnakeHead, SnakeSegments, TickStage,
+    TickStageLabel,
+};
+use std::time::Duration;
+
+use SnakeDirection::*;
+
+fn app() -> App {
+    app_in(GameMode::Classic)
+}
+
+fn app_in(mode: GameMode) -> App {
+    app_with(mode, Levels::default())
+}
+
+// the levels mode, on a board made up for the test
+fn level_app(rules: Rules) -> App {
+    let level = Level {
+        name: "test".to_string(),
+        rules,
+    };
+
+    app_with(
+        GameMode::Levels,
+        Levels {
+            list: vec![level],
+            current: 0,
+        },
+    )
+}
+
+fn app_with(mode: GameMode, levels: Levels) -> App {
+    let mut app = App::new();
+
+    app.add_plugins(MinimalPlugins)
+        // only here so the startup systems have an asset server to ask for fonts
+        .add_plugin(AssetPlugin)
+        // fresh ones, so the player's own files are never read or written
+        .insert_resource(Config { mode, ..default() })
+        .insert_resource(HighScores::default())
+        .insert_resource(DailyResults::default())
+        .insert_resource(SaveFile::default())
+        .insert_resource(Scripts::default())
+        .insert_resource(Stats::default())
+        .insert_resource(Screenshots::default())
+        .insert_resource(Puzzles::default())
+        .insert_resource(levels)
+        .insert_resource(Theme::default())
+        .insert_resource(Themes::default())
+        .insert_resource(Locale::default())
+        .add_plugin(LocalePlugin)
+        .add_plugin(MenuPlugin)
+        .insert_resource(Input::<KeyCode>::default())
+        .insert_resource(ManualTicks::default())
+        .add_plugin(SetupPlugin)
+        .add_plugin(LayoutPlugin)
+        .add_plugin(AccessibilityPlugin)
+        .add_plugin(TickPlugin)
+        .add_plugin(GameplayPlugin)
+        .add_plugin(GameRenderPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(ModePlugin)
+        .add_plugin(PuzzlePlugin)
+        .add_plugin(CreaturePlugin)
+        .add_plugin(PortalPlugin)
+        .add_plugin(ScriptPlugin)
+        .add_plugin(DailyPlugin)
+        .add_plugin(RewindPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(RecordPlugin)
+        .add_plugin(ScreenshotPlugin);
+
+    // startup. nothing's ticked yet, so there's no food either
+    app.update();
+
+    app
+}
+
+fn clear_food(app: &mut App) {
+    let food = app
+        .world
+        .query_filtered::<(Entity, &Position), With<Food>>()
+        .iter(&app.world)
+        .map(|(ent, pos)| (ent, *pos))
+        .collect::<Vec<_>>();
+
+    for (ent, pos) in food {
+        app.world.resource_mut::<OccupancyGrid>().set(pos.x, pos.y, Cell::Empty);
+        app.world.despawn(ent);
+    }
+}
+
+// swaps whatever food there is for one at (x, y)
+fn place_food(app: &mut App, x: i32, y: i32) {
+    clear_food(app);
+
+    app.world.resource_mut::<OccupancyGrid>().set(x, y, Cell::Food);
+    app.world
+        .spawn()
+        .insert_bundle(SpriteBundle::default())
+        .insert(Position { x, y })
+        .insert(Food)
+        .insert(FoodKind::Normal);
+}
+
+// holds down one key, and only that key
+fn press(app: &mut App, key: KeyCode) {
+    let mut input = app.world.resource_mut::<Input<KeyCode>>();
+    input.release_all();
+    input.press(key);
+}
+
+// one frame with one tick in it
+fn tick(app: &mut App) {
+    app.world.resource_mut::<ManualTicks>().0 += 1;
+    app.update();
+}
+
+// moves the snake n cells, dropping whatever food got spawned along the way
+// so it can't end up in the snake's path
+fn step(app: &mut App, n: u32) {
+    for _ in 0..n {
+        tick(app);
+        clear_food(app);
+    }
+}
+
+// turns and takes a step
+fn go(app: &mut App, key: KeyCode) {
+    press(app, key);
+    step(app, 1);
+}
+
+fn head(app: &mut App) -> Position {
+    *app.world
+        .query_filtered::<&Position, With<SnakeHead>>()
+        .single(&app.world)
+}
+
+fn head_direction(app: &mut App) -> DirectionPair {
+    *app.world
+        .query_filtered::<&DirectionPair, With<SnakeHead>>()
+        .single(&app.world)
+}
+
+fn length(app: &App) -> usize {
+    app.world.resource::<SnakeSegments>().len()
+}
+
+fn score(app: &App) -> u128 {
+    app.world.resource::<Score>().0
+}
+
+fn food(app: &mut App) -> Vec<Position> {
+    app.world
+        .query_filtered::<&Position, With<Food>>()
+        .iter(&app.world)
+        .copied()
+        .collect()
+}
+
+#[test]
+fn starts_still() {
+    let mut app = app();
+
+    step(&mut app, 5);
+
+    assert_eq!(head(&mut app), Position { x: 3, y: 3 });
+    assert_eq!(length(&app), 3);
+    assert_eq!(score(&app), 0);
+}
+
+#[test]
+fn eating_grows_and_scores() {
+    let mut app = app();
+
+    place_food(&mut app, 3, 4);
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+
+    assert_eq!(head(&mut app), Position { x: 3, y: 4 });
+    assert_eq!(score(&app), 1);
+    assert_eq!(length(&app), 4);
+
+    // the eaten food's gone and a new one's somewhere that isn't the snake
+    let food = food(&mut app);
+    assert_eq!(food.len(), 1);
+    assert_eq!(
+        app.world.resource::<OccupancyGrid>().get(food[0].x, food[0].y),
+        Cell::Food
+    );
+
+    // the new tail lands where the old one just left
+    let tail = *app.world.resource::<SnakeSegments>().back().unwrap();
+    assert_eq!(
+        *app.world.get::<Position>(tail).unwrap(),
+        Position { x: 3, y: 1 }
+    );
+}
+
+#[test]
+fn wall_costs_the_tail() {
+    let mut app = app();
+
+    // three steps to the edge, the fourth bumps into it
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 3);
+    assert_eq!(head(&mut app), Position { x: 0, y: 3 });
+    assert_eq!(length(&app), 3);
+
+    step(&mut app, 1);
+    assert_eq!(head(&mut app), Position { x: 0, y: 3 });
+    assert_eq!(length(&app), 2);
+    assert!(app.world.resource::<GameOver>().0.is_none());
+}
+
+#[test]
+fn wall_kills_a_lone_head() {
+    let mut app = app();
+
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 3 + 3);
+
+    assert_eq!(length(&app), 0);
+    assert!(app.world.resource::<GameOver>().0.is_some());
+}
+
+#[test]
+fn running_into_yourself_costs_the_tail() {
+    let mut app = app();
+
+    // grow to five so there's enough snake to curl into
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+    place_food(&mut app, 3, 5);
+    go(&mut app, KeyCode::Up);
+    assert_eq!(length(&app), 5);
+
+    go(&mut app, KeyCode::Right);
+    go(&mut app, KeyCode::Down);
+    assert_eq!(head(&mut app), Position { x: 4, y: 4 });
+
+    // (3, 4) is body
+    go(&mut app, KeyCode::Left);
+    assert_eq!(head(&mut app), Position { x: 4, y: 4 });
+    assert_eq!(length(&app), 4);
+    assert_eq!(score(&app), 2);
+}
+
+#[test]
+fn cant_reverse() {
+    let mut app = app();
+
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Down);
+
+    assert_eq!(head_direction(&mut app).1, Up);
+    assert_eq!(head(&mut app), Position { x: 3, y: 5 });
+
+    go(&mut app, KeyCode::Left);
+    go(&mut app, KeyCode::Right);
+
+    assert_eq!(head_direction(&mut app).1, Left);
+    assert_eq!(head(&mut app), Position { x: 1, y: 5 });
+}
+
+#[test]
+fn corner_textures() {
+    let mut app = app();
+
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Up);
+
+    // steer the head directly, so this doesn't lean on the corner fix in snake_controls
+    app.world.resource_mut::<Input<KeyCode>>().release_all();
+    *app.world
+        .query_filtered::<&mut DirectionPair, With<SnakeHead>>()
+        .single_mut(&mut app.world) = DirectionPair(Up, Right);
+    step(&mut app, 1);
+
+    let theme = Theme::default();
+    let segments = app.world.resource::<SnakeSegments>().clone();
+
+    // the neck is the corner the head just went round
+    let neck = app.world.get::<TextureAtlasSprite>(segments[1]).unwrap();
+    assert_eq!(neck.index, theme.tiles.corners.down_right);
+
+    // every other segment matches what its dirpair says it should be
+    for (i, segment) in segments.iter().enumerate().skip(1) {
+        let dir = *app.world.get::<DirectionPair>(*segment).unwrap();
+        let sprite = app.world.get::<TextureAtlasSprite>(*segment).unwrap();
+
+        assert_eq!(sprite.index, theme.tiles.part(segments.part(i), dir));
+    }
+
+    let tail = app.world.get::<TextureAtlasSprite>(segments[2]).unwrap();
+    assert_eq!(tail.index, theme.tiles.tail.up);
+}
+
+#[test]
+fn positions_reach_the_screen_without_a_window() {
+    let mut app = app();
+
+    go(&mut app, KeyCode::Up);
+
+    let head = app
+        .world
+        .query_filtered::<&Transform, With<SnakeHead>>()
+        .single(&app.world)
+        .translation;
+
+    let layout = app.world.resource::<crate::layout::BoardLayout>();
+    assert_eq!(head.truncate(), layout.cell_center(Position { x: 3, y: 4 }));
+}
+
+#[test]
+fn ticks_are_counted() {
+    let mut app = app();
+
+    assert_eq!(app.world.resource::<Tick>().0, 0);
+
+    step(&mut app, 4);
+    assert_eq!(app.world.resource::<Tick>().0, 4);
+
+    // several owed at once all run in the same frame
+    app.world.resource_mut::<ManualTicks>().0 = 3;
+    app.update();
+    assert_eq!(app.world.resource::<Tick>().0, 7);
+
+    // and none while the menu's up
+    app.world.resource_mut::<Menu>().open = true;
+    tick(&mut app);
+    assert_eq!(app.world.resource::<Tick>().0, 7);
+}
+
+#[test]
+fn first_tick_puts_food_down() {
+    let mut app = app();
+
+    assert!(food(&mut app).is_empty());
+
+    tick(&mut app);
+    assert_eq!(food(&mut app).len(), 1);
+}
+
+#[test]
+fn clock_catches_up_after_a_hitch() {
+    let mut clock = TickClock::default();
+    clock.step = Duration::from_millis(100);
+
+    assert_eq!(clock.advance(Duration::from_millis(60)), 0);
+    assert_eq!(clock.advance(Duration::from_millis(60)), 1);
+
+    // 20 left over plus 300 is three more, with 20 still banked
+    assert_eq!(clock.advance(Duration::from_millis(300)), 3);
+    assert_eq!(clock.advance(Duration::from_millis(80)), 1);
+}
+
+#[test]
+fn clock_catch_up_is_capped() {
+    let mut clock = TickClock::default();
+    clock.step = Duration::from_millis(100);
+
+    assert_eq!(clock.advance(Duration::from_secs(10)), MAX_CATCH_UP);
+
+    // and the rest of the hitch is forgotten
+    assert_eq!(clock.advance(Duration::from_millis(50)), 0);
+}
+
+fn game_over(app: &App) -> bool {
+    app.world.resource::<GameOver>().0.is_some()
+}
+
+#[test]
+fn zen_never_takes_the_head() {
+    let mut app = app_in(GameMode::Zen);
+
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 3 + 10);
+
+    assert_eq!(length(&app), 1);
+    assert_eq!(head(&mut app), Position { x: 0, y: 3 });
+    assert!(!game_over(&app));
+}
+
+#[test]
+fn survival_bumps_are_fatal() {
+    let mut app = app_in(GameMode::Survival);
+
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 3);
+    assert!(!game_over(&app));
+
+    step(&mut app, 1);
+    assert!(game_over(&app));
+    assert_eq!(length(&app), 3);
+}
+
+#[test]
+fn survival_grows_by_itself() {
+    let mut app = app_in(GameMode::Survival);
+
+    // up and down the board until it's time to grow
+    press(&mut app, KeyCode::Right);
+    step(&mut app, 1);
+    for i in 1..SURVIVAL_GROW_TICKS {
+        press(&mut app, ifelse!((i / 15) % 2 == 0, KeyCode::Up, KeyCode::Down));
+        step(&mut app, 1);
+
+        if i == 14 || i == 29 {
+            press(&mut app, KeyCode::Right);
+            step(&mut app, 1);
+        }
+    }
+
+    assert_eq!(length(&app), 4);
+    assert!(!game_over(&app));
+
+    // and score is time alive, not food
+    let round = app.world.resource::<Round>().clone();
+    assert_eq!(score(&app), round.elapsed.as_secs() as u128);
+}
+
+#[test]
+fn time_attack_runs_out() {
+    let mut app = app_in(GameMode::TimeAttack(60));
+
+    press(&mut app, KeyCode::Right);
+    step(&mut app, 1);
+    assert!(app.world.resource::<Round>().time_left().unwrap() < Duration::from_secs(60));
+
+    // pretend most of the minute's gone
+    app.world.resource_mut::<Round>().elapsed = Duration::from_secs(59);
+    step(&mut app, 10);
+
+    assert_eq!(
+        app.world.resource::<GameOver>().0,
+        Some(crate::Outcome::TimeUp)
+    );
+}
+
+#[test]
+fn high_scores_are_per_mode() {
+    let mut app = app_in(GameMode::Zen);
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+    place_food(&mut app, 3, 5);
+    go(&mut app, KeyCode::Up);
+
+    let high_scores = app.world.resource::<HighScores>();
+    assert_eq!(high_scores.best("zen"), 2);
+    assert_eq!(high_scores.best("classic"), 0);
+}
+
+#[test]
+fn new_game_starts_over() {
+    let mut app = app();
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 3 + 4);
+    assert!(game_over(&app));
+
+    app.world.resource_mut::<Config>().mode = GameMode::Zen;
+    app.world.send_event(NewGameEvent);
+    app.update();
+
+    assert!(!game_over(&app));
+    assert_eq!(score(&app), 0);
+    assert_eq!(length(&app), 3);
+    assert_eq!(head(&mut app), Position { x: 3, y: 3 });
+    assert_eq!(app.world.resource::<Round>().mode, GameMode::Zen);
+    assert_eq!(app.world.resource::<OccupancyGrid>().free_count(), 20 * 20 - 3);
+
+    // the old best sticks around
+    assert_eq!(app.world.resource::<HighScores>().best("classic"), 1);
+}
+
+#[test]
+fn dates_count_from_the_epoch() {
+    let epoch = Date::from_days(0);
+    assert_eq!(epoch.to_string(), "1970-01-01");
+    assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+    assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+
+    for days in (-100_000..100_000).step_by(97) {
+        assert_eq!(Date::from_days(days).days(), days);
+    }
+}
+
+#[test]
+fn daily_board_only_depends_on_the_date() {
+    let today = Date::today();
+    assert_eq!(daily_rules(today), daily_rules(today));
+
+    // a week's worth shouldn't all be the same board
+    let week = (0..7)
+        .map(|i| daily_rules(Date::from_days(today.days() - i)))
+        .collect::<Vec<_>>();
+    assert!(week.iter().any(|day| *day != week[0]));
+
+    for (_, rules) in week {
+        for wall in rules.walls {
+            assert!(wall.x < rules.width && wall.y < rules.height);
+            // clear of the snake's starting spot
+            assert!(wall.x > 6 || wall.y > 6);
+        }
+    }
+}
+
+#[test]
+fn daily_mode_plays_todays_board() {
+    let mut app = app_in(GameMode::Daily);
+
+    let round = app.world.resource::<Round>().clone();
+    let (seed, rules) = daily_rules(Date::today());
+    assert_eq!(round.date, Some(Date::today()));
+    assert_eq!(round.seed, seed);
+
+    let grid = app.world.resource::<OccupancyGrid>();
+    assert_eq!((grid.width(), grid.height()), (rules.width, rules.height));
+    for wall in rules.walls.iter() {
+        assert_eq!(grid.get(wall.x, wall.y), Cell::Wall);
+    }
+
+    // and the first food lands in the same place for everyone
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    let first = food(&mut app);
+
+    let mut other = app_in(GameMode::Daily);
+    press(&mut other, KeyCode::Up);
+    tick(&mut other);
+    assert_eq!(food(&mut other), first);
+}
+
+#[test]
+fn daily_results_keep_the_best_and_count_streaks() {
+    let today = Date::today();
+    let day = |back: i64| Date::from_days(today.days() - back);
+    let replay = Default::default();
+
+    let mut results = DailyResults::default();
+    assert_eq!(results.streak(today), 0);
+
+    assert!(results.submit(day(1), 5, &replay));
+    assert!(!results.submit(day(1), 3, &replay));
+    assert_eq!(results.get(day(1)).unwrap().score, 5);
+
+    // not having played today yet doesn't break it
+    results.submit(day(2), 1, &replay);
+    results.submit(day(4), 1, &replay);
+    assert_eq!(results.streak(today), 2);
+
+    results.submit(today, 0, &replay);
+    assert_eq!(results.streak(today), 3);
+}
+
+#[test]
+fn rewind_goes_back_a_tick_at_a_time() {
+    let mut app = app();
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Right);
+    go(&mut app, KeyCode::Right);
+    assert_eq!(head(&mut app), Position { x: 5, y: 4 });
+
+    press(&mut app, KeyCode::Back);
+    tick(&mut app);
+    assert_eq!(head(&mut app), Position { x: 4, y: 4 });
+
+    // all the way back to before the food, food and all
+    tick(&mut app);
+    tick(&mut app);
+    assert_eq!(head(&mut app), Position { x: 3, y: 3 });
+    assert_eq!(length(&app), 3);
+    assert_eq!(score(&app), 0);
+    assert_eq!(food(&mut app), vec![Position { x: 3, y: 4 }]);
+    assert_eq!(app.world.resource::<Round>().ticks, 0);
+    assert_eq!(app.world.resource::<OccupancyGrid>().free_count(), 20 * 20 - 4);
+
+    // nothing left to undo, so the game carries on from there
+    assert!(!app.world.resource::<Rewind>().active);
+    go(&mut app, KeyCode::Up);
+    assert_eq!(head(&mut app), Position { x: 3, y: 4 });
+    assert_eq!(score(&app), 1);
+}
+
+#[test]
+fn rewind_brings_back_the_rng() {
+    let mut app = app();
+
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    let before = *app.world.resource::<GameRng>();
+
+    // eating makes the food rng roll
+    let ahead = head(&mut app).step(Up);
+    place_food(&mut app, ahead.x, ahead.y);
+    tick(&mut app);
+    assert_ne!(*app.world.resource::<GameRng>(), before);
+
+    press(&mut app, KeyCode::Back);
+    tick(&mut app);
+    assert_eq!(*app.world.resource::<GameRng>(), before);
+}
+
+#[test]
+fn rewind_undoes_a_game_over() {
+    let mut app = app();
+
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 3 + 3);
+    assert!(game_over(&app));
+
+    press(&mut app, KeyCode::Back);
+    tick(&mut app);
+    assert!(!game_over(&app));
+    assert_eq!(length(&app), 1);
+}
+
+#[test]
+fn ranked_modes_cant_rewind() {
+    let mut app = app_in(GameMode::TimeAttack(60));
+
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Up);
+    assert_eq!(app.world.resource::<Rewind>().depth(), 0);
+
+    press(&mut app, KeyCode::Back);
+    step(&mut app, 1);
+    assert_eq!(head(&mut app), Position { x: 3, y: 6 });
+}
+
+#[test]
+fn zen_rewinds_without_a_limit() {
+    let mut zen = app_in(GameMode::Zen);
+    zen.world.resource_mut::<Config>().rewind_ticks = 2;
+
+    go(&mut zen, KeyCode::Up);
+    step(&mut zen, 9);
+    assert_eq!(zen.world.resource::<Rewind>().depth(), 10);
+
+    // classic sticks to the setting
+    let mut app = app();
+    app.world.resource_mut::<Config>().rewind_ticks = 2;
+
+    go(&mut app, KeyCode::Up);
+    step(&mut app, 9);
+    assert_eq!(app.world.resource::<Rewind>().depth(), 2);
+}
+
+// somewhere the tests can write saves without touching the real one
+fn temp_save(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("snek-{}-{}.ron", name, std::process::id()))
+}
+
+#[test]
+fn save_and_resume() {
+    let path = temp_save("resume");
+    let mut app = app();
+    app.world.resource_mut::<SaveFile>().path = Some(path.clone());
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Right);
+    place_food(&mut app, 10, 10);
+
+    let rng = *app.world.resource::<GameRng>();
+    let free = app.world.resource::<OccupancyGrid>().free_count();
+
+    app.world.send_event(SaveGameEvent);
+    app.update();
+    assert_eq!(app.world.resource::<Events<bevy::app::AppExit>>().len(), 1);
+
+    // a fresh game somewhere else, then the save on top of it
+    let mut app = app_in(GameMode::Zen);
+    app.world.resource_mut::<SaveFile>().path = Some(path.clone());
+    app.world.send_event(MenuEvent::Activate("load_game"));
+    app.update();
+
+    assert_eq!(head(&mut app), Position { x: 4, y: 4 });
+    assert_eq!(head_direction(&mut app).1, Right);
+    assert_eq!(length(&app), 4);
+    assert_eq!(score(&app), 1);
+    assert_eq!(food(&mut app), vec![Position { x: 10, y: 10 }]);
+    assert_eq!(*app.world.resource::<GameRng>(), rng);
+    assert_eq!(app.world.resource::<Round>().mode, GameMode::Classic);
+    assert_eq!(app.world.resource::<Config>().mode, GameMode::Classic);
+    assert_eq!(app.world.resource::<OccupancyGrid>().free_count(), free);
+
+    // and it carries on from there
+    step(&mut app, 1);
+    assert_eq!(head(&mut app), Position { x: 5, y: 4 });
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn old_saves_are_turned_away() {
+    let path = temp_save("old");
+    let mut app = app();
+    app.world.resource_mut::<SaveFile>().path = Some(path.clone());
+
+    go(&mut app, KeyCode::Up);
+    app.world.send_event(SaveGameEvent);
+    app.update();
+
+    let mut save = read_save(&path).unwrap();
+    save.version = SAVE_VERSION + 1;
+    write_save(&path, &save).unwrap();
+
+    assert_eq!(
+        read_save(&path).unwrap_err(),
+        SaveError::Version {
+            found: SAVE_VERSION + 1,
+            expected: SAVE_VERSION
+        }
+    );
+
+    // the menu says why, in the player's language, and the game's left alone
+    let spanish = parse_language("es", &std::fs::read_to_string("assets/lang/es.txt").unwrap());
+    let mut locale = app.world.resource_mut::<Locale>();
+    locale.list.push(spanish.unwrap());
+    locale.select(1);
+
+    let before = head(&mut app);
+    app.world.send_event(MenuEvent::Activate("load_game"));
+    app.update();
+    assert_eq!(head(&mut app), before);
+    assert_eq!(
+        app.world.resource::<SaveFile>().status,
+        Some(format!(
+            "el archivo de guardado es de la versión {}, esta versión solo lee la {}",
+            SAVE_VERSION + 1,
+            SAVE_VERSION
+        ))
+    );
+
+    let _ = std::fs::remove_file(path);
+}
+
+fn creatures(app: &mut App) -> Vec<(Position, Creature)> {
+    app.world
+        .query::<(&Position, &Creature)>()
+        .iter(&app.world)
+        .map(|(pos, creature)| (*pos, creature.clone()))
+        .collect()
+}
+
+#[test]
+fn level_files_load() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/levels");
+
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let level = read_level(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+        // nothing where the snake starts out
+        let rules = level.rules;
+        let mut taken = rules.walls.clone();
+        taken.extend(rules.mice.iter().copied());
+        taken.extend(rules.creatures.iter().map(|creature| creature.start()));
+        taken.extend(rules.portals.iter().flat_map(|(a, b)| [*a, *b]));
+
+        for pos in taken {
+            assert!(pos.x >= 0 && pos.x < rules.width && pos.y >= 0 && pos.y < rules.height);
+            assert!(pos.x != 3 || pos.y > 3, "{} blocks the start", path.display());
+        }
+    }
+}
+
+#[test]
+fn patrols_walk_back_and_forth() {
+    let mut app = level_app(Rules {
+        creatures: vec![Creature::Patrol {
+            path: vec![Position { x: 10, y: 5 }, Position { x: 12, y: 5 }],
+            target: 0,
+            backwards: false,
+        }],
+        ..default()
+    });
+
+    // waits for the snake like everything else
+    step(&mut app, 2);
+    assert_eq!(creatures(&mut app)[0].0, Position { x: 10, y: 5 });
+
+    press(&mut app, KeyCode::Right);
+    let mut seen = Vec::new();
+    for _ in 0..4 {
+        step(&mut app, 1);
+        seen.push(creatures(&mut app)[0].0.x);
+    }
+    assert_eq!(seen, vec![11, 12, 11, 10]);
+
+    let grid = app.world.resource::<OccupancyGrid>();
+    assert_eq!(grid.get(10, 5), Cell::Wall);
+    assert_eq!(grid.get(11, 5), Cell::Empty);
+}
+
+#[test]
+fn patrols_cost_the_tail() {
+    let mut app = level_app(Rules {
+        creatures: vec![Creature::Patrol {
+            path: vec![Position { x: 3, y: 5 }],
+            target: 0,
+            backwards: false,
+        }],
+        ..default()
+    });
+
+    go(&mut app, KeyCode::Up);
+    step(&mut app, 1);
+    assert_eq!(head(&mut app), Position { x: 3, y: 4 });
+    assert_eq!(length(&app), 2);
+}
+
+#[test]
+fn mice_run_and_come_back() {
+    let mut app = level_app(Rules {
+        mice: vec![Position { x: 3, y: 5 }],
+        ..default()
+    });
+
+    // mice move every other tick, before the snake does
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    assert_eq!(food(&mut app), vec![Position { x: 3, y: 5 }]);
+
+    tick(&mut app);
+    assert_eq!(food(&mut app), vec![Position { x: 3, y: 6 }]);
+    assert_eq!(app.world.resource::<OccupancyGrid>().get(3, 5), Cell::Snake);
+
+    // caught it
+    tick(&mut app);
+    assert_eq!(score(&app), FoodKind::Mouse.points());
+    assert_eq!(length(&app), 4);
+
+    let kinds = app
+        .world
+        .query::<&FoodKind>()
+        .iter(&app.world)
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, vec![FoodKind::Mouse]);
+}
+
+#[test]
+fn rivals_go_for_the_food() {
+    let body = [(10, 10), (10, 11), (10, 12)].map(|(x, y)| Position { x, y });
+    let mut app = level_app(Rules {
+        creatures: vec![Creature::Rival {
+            body: body.into_iter().collect(),
+            grow: 0,
+        }],
+        ..default()
+    });
+
+    place_food(&mut app, 10, 7);
+    press(&mut app, KeyCode::Right);
+    tick(&mut app);
+    tick(&mut app);
+    tick(&mut app);
+
+    let (pos, rival) = creatures(&mut app).remove(0);
+    assert_eq!(pos, Position { x: 10, y: 7 });
+    assert_eq!(score(&app), 0);
+
+    // the food's gone to the rival and a new one's out
+    let food = food(&mut app);
+    assert_eq!(food.len(), 1);
+    assert_ne!(food[0], pos);
+
+    match rival {
+        Creature::Rival { body, grow } => {
+            assert_eq!(body.len(), 3);
+            assert_eq!(grow, 1);
+        }
+        _ => panic!("not a rival"),
+    }
+
+    // the player can't go through it
+    let grid = app.world.resource::<OccupancyGrid>();
+    assert_eq!(grid.get(10, 8), Cell::Snake);
+    assert_eq!(grid.get(10, 10), Cell::Empty);
+}
+
+#[test]
+fn creatures_rewind_too() {
+    let mut app = level_app(Rules {
+        creatures: vec![Creature::Patrol {
+            path: vec![Position { x: 10, y: 5 }, Position { x: 15, y: 5 }],
+            target: 0,
+            backwards: false,
+        }],
+        ..default()
+    });
+
+    go(&mut app, KeyCode::Up);
+    step(&mut app, 2);
+    assert_eq!(creatures(&mut app)[0].0, Position { x: 13, y: 5 });
+
+    press(&mut app, KeyCode::Back);
+    tick(&mut app);
+    tick(&mut app);
+    assert_eq!(creatures(&mut app)[0].0, Position { x: 11, y: 5 });
+
+    let grid = app.world.resource::<OccupancyGrid>();
+    assert_eq!(grid.get(11, 5), Cell::Wall);
+    assert_eq!(grid.get(13, 5), Cell::Empty);
+}
+
+// one pair, the near end right up the snake's column
+fn portal_rules() -> Rules {
+    Rules {
+        portals: vec![(Position { x: 3, y: 5 }, Position { x: 12, y: 8 })],
+        ..default()
+    }
+}
+
+#[test]
+fn portals_carry_the_snake_through() {
+    let mut app = level_app(portal_rules());
+
+    go(&mut app, KeyCode::Up);
+    assert_eq!(head(&mut app), Position { x: 3, y: 4 });
+
+    // in at (3, 5), out the top of its partner, still going up
+    step(&mut app, 1);
+    assert_eq!(head(&mut app), Position { x: 12, y: 9 });
+    assert_eq!(head_direction(&mut app).1, Up);
+    assert_eq!(length(&app), 3);
+
+    // the body comes through behind it, never standing on a portal either
+    step(&mut app, 2);
+    let segments = app.world.resource::<SnakeSegments>().clone();
+    let body = segments
+        .iter()
+        .map(|ent| *app.world.get::<Position>(*ent).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        body,
+        [(12, 11), (12, 10), (12, 9)].map(|(x, y)| Position { x, y })
+    );
+
+    let grid = app.world.resource::<OccupancyGrid>();
+    assert_eq!(grid.get(3, 5), Cell::Portal);
+    assert_eq!(grid.get(12, 8), Cell::Portal);
+    assert_eq!(grid.get(3, 4), Cell::Empty);
+}
+
+#[test]
+fn straddling_a_portal_keeps_the_sprite_straight() {
+    let mut app = level_app(portal_rules());
+
+    go(&mut app, KeyCode::Up);
+    step(&mut app, 1);
+
+    // the neck's on the far side of the portal from the head, and still a straight piece
+    let theme = Theme::default();
+    let neck = app.world.resource::<SnakeSegments>()[1];
+    assert_eq!(*app.world.get::<Position>(neck).unwrap(), Position { x: 3, y: 4 });
+    assert_eq!(
+        app.world.get::<TextureAtlasSprite>(neck).unwrap().index,
+        theme.tiles.part(app.world.resource::<SnakeSegments>().part(1), DirectionPair(Up, Up))
+    );
+}
+
+#[test]
+fn a_blocked_exit_is_a_bump() {
+    let mut app = level_app(Rules {
+        walls: vec![Position { x: 12, y: 9 }],
+        ..portal_rules()
+    });
+
+    go(&mut app, KeyCode::Up);
+    step(&mut app, 1);
+    assert_eq!(head(&mut app), Position { x: 3, y: 4 });
+    assert_eq!(length(&app), 2);
+}
+
+#[test]
+fn no_food_on_portals() {
+    // a little board that's all portals, bar the snake and one cell
+    let cells = (0..6)
+        .flat_map(|y| (0..4).map(move |x| Position { x, y }))
+        .filter(|pos| pos.x != 3 || !(1..=3).contains(&pos.y))
+        .filter(|pos| *pos != Position { x: 2, y: 0 })
+        .collect::<Vec<_>>();
+
+    let mut app = level_app(Rules {
+        width: 4,
+        height: 6,
+        portals: cells.chunks(2).map(|pair| (pair[0], pair[1])).collect(),
+        ..default()
+    });
+    tick(&mut app);
+
+    assert_eq!(food(&mut app), vec![Position { x: 2, y: 0 }]);
+}
+
+fn console(app: &App) -> Vec<String> {
+    app.world.resource::<Scripts>().console.iter().cloned().collect()
+}
+
+#[test]
+fn broken_scripts_go_to_the_console() {
+    let mut app = app();
+
+    app.world.resource_mut::<Scripts>().add("broken.rhai", "fn on_tick(board) {");
+    app.world
+        .resource_mut::<Scripts>()
+        .add("throws.rhai", "fn on_tick(board) { throw \"nope\"; }");
+
+    go(&mut app, KeyCode::Up);
+    step(&mut app, 2);
+
+    let console = console(&app);
+    assert!(console[0].starts_with("broken.rhai: "));
+    assert!(console[1].contains("nope"));
+    // failing every tick still only says so once
+    assert_eq!(console.len(), 2);
+    assert_eq!(head(&mut app), Position { x: 3, y: 6 });
+}
+
+#[test]
+fn scripts_pick_where_food_goes() {
+    let mut app = app();
+
+    app.world.resource_mut::<Scripts>().add(
+        "test.rhai",
+        "fn spawn_food_position(board) { #{x: board.head.x, y: board.head.y + 2} }",
+    );
+
+    tick(&mut app);
+    assert_eq!(food(&mut app), vec![Position { x: 3, y: 5 }]);
+}
+
+#[test]
+fn scripts_hear_about_eating_and_bumps() {
+    let mut app = app();
+
+    app.world.resource_mut::<Scripts>().add(
+        "test.rhai",
+        r#"
+            fn on_eat(kind) { spawn_wall(10, 10); print(kind); }
+            fn on_collision(cause) { print(cause); }
+            fn speed_for(score) { 40 + score }
+        "#,
+    );
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+
+    assert_eq!(app.world.resource::<OccupancyGrid>().get(10, 10), Cell::Wall);
+    assert!(app.world.resource::<Round>().rules.walls.contains(&Position { x: 10, y: 10 }));
+    assert_eq!(app.world.resource::<TickClock>().step, Duration::from_millis(41));
+
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 5);
+
+    assert_eq!(console(&app), vec!["normal", "edge"]);
+}
+
+#[test]
+fn silly_speeds_from_scripts_are_kept_in_range() {
+    for answer in ["1e300", "1e308 * 10.0"] {
+        let mut app = app();
+        let script = format!("fn speed_for(score) {{ {} }}", answer);
+
+        app.world.resource_mut::<Scripts>().add("test.rhai", &script);
+
+        go(&mut app, KeyCode::Up);
+        assert_eq!(app.world.resource::<TickClock>().step, Duration::from_secs(10));
+        assert!(console(&app)[0].starts_with("speed_for: out of range"), "{}", answer);
+    }
+}
+
+#[test]
+fn example_mods_compile() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("mods/examples");
+    let mut scripts = Scripts::default();
+
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        scripts.add(&path.display().to_string(), &std::fs::read_to_string(&path).unwrap());
+    }
+
+    assert!(scripts.console.is_empty(), "{:?}", scripts.console);
+}
+
+// what a system from another crate saw, in between the game's own
+#[derive(Default)]
+struct Seen(Vec<(Position, u128)>);
+
+fn watch(mut seen: ResMut<Seen>, head: Query<&Position, With<SnakeHead>>, score: Res<Score>) {
+    seen.0.push((*head.single(), score.0));
+}
+
+#[test]
+fn labels_order_outside_systems() {
+    let mut app = app();
+
+    app.insert_resource(Seen::default())
+        .stage(TickStageLabel, |stage: &mut TickStage| {
+            stage.add_system_set(
+                SystemSet::new().with_system(watch.after(GameSystem::Move).before(GameSystem::Score)),
+            )
+        });
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+
+    // the head's already moved onto the food, but it's not been scored yet
+    assert_eq!(app.world.resource::<Seen>().0, vec![(Position { x: 3, y: 4 }, 0)]);
+    assert_eq!(score(&app), 1);
+}
+
+#[test]
+fn custom_food_scores_its_own_points() {
+    let mut app = app();
+    let kind = FoodKind::Custom { id: 7, points: 5 };
+
+    clear_food(&mut app);
+    app.world.resource_mut::<OccupancyGrid>().set(3, 4, Cell::Food);
+    app.world
+        .spawn()
+        .insert_bundle(SpriteBundle::default())
+        .insert(Position { x: 3, y: 4 })
+        .insert(Food)
+        .insert(kind);
+
+    app.insert_resource(Vec::<FoodKind>::new());
+    app.add_system(|mut reader: EventReader<ScoredEvent>, mut eaten: ResMut<Vec<FoodKind>>| {
+        eaten.extend(reader.iter().map(|ScoredEvent(_, kind)| *kind));
+    });
+
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    // the tick stage runs after Update, so the event's read next frame
+    app.update();
+
+    assert_eq!(score(&app), 5);
+    assert_eq!(length(&app), 4);
+    assert_eq!(*app.world.resource::<Vec<FoodKind>>(), vec![kind]);
+}
+
+fn lifetime(app: &App) -> crate::stats::Lifetime {
+    app.world.resource::<Stats>().lifetime.clone()
+}
+
+#[test]
+fn stats_count_food_and_unlock() {
+    let mut app = app();
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Left);
+
+    let lifetime = lifetime(&app);
+    assert_eq!(lifetime.food_eaten, 1);
+    assert_eq!(lifetime.longest_snake, 4);
+    assert_eq!(lifetime.turns, 1);
+    assert!(lifetime.unlocked.contains("first_bite"));
+    assert_eq!(
+        app.world.resource::<Stats>().toasts,
+        vec!["achievement unlocked: first bite".to_string()]
+    );
+}
+
+#[test]
+fn rewound_food_isnt_counted_twice() {
+    let mut app = app();
+
+    place_food(&mut app, 3, 4);
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Right);
+
+    press(&mut app, KeyCode::Back);
+    tick(&mut app);
+    tick(&mut app);
+    assert_eq!(app.world.resource::<Round>().ticks, 0);
+
+    // the same food and the same turn again
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Right);
+    assert_eq!(score(&app), 1);
+    assert_eq!(lifetime(&app).food_eaten, 1);
+    assert_eq!(lifetime(&app).turns, 1);
+
+    // and past where it got to before counts as usual
+    place_food(&mut app, 5, 4);
+    go(&mut app, KeyCode::Right);
+    assert_eq!(lifetime(&app).food_eaten, 2);
+}
+
+#[test]
+fn stats_count_deaths_by_cause() {
+    let mut app = app_in(GameMode::Survival);
+
+    press(&mut app, KeyCode::Left);
+    step(&mut app, 4);
+    assert!(game_over(&app));
+    app.update();
+
+    assert_eq!(lifetime(&app).games_played, 1);
+    assert_eq!(lifetime(&app).deaths.get("edge"), Some(&1));
+
+    // only counted the once
+    app.update();
+    assert_eq!(lifetime(&app).games_played, 1);
+}
+
+#[test]
+fn turning_twice_in_a_row_breaks_the_streak() {
+    let mut app = app();
+
+    // sets off up and eats straight away
+    place_food(&mut app, 3, 4);
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    assert_eq!(lifetime(&app).best_steady, 1);
+
+    // right on the very next tick, then up on the one after: two turns in a row,
+    // so the food after them starts the count over
+    place_food(&mut app, 10, 10);
+    press(&mut app, KeyCode::Right);
+    tick(&mut app);
+    assert_eq!(head(&mut app), Position { x: 4, y: 4 });
+
+    place_food(&mut app, 4, 5);
+    go(&mut app, KeyCode::Up);
+    assert_eq!(lifetime(&app).best_steady, 1);
+    assert_eq!(lifetime(&app).food_eaten, 2);
+}
+
+#[test]
+fn frames_draw_like_the_screen() {
+    let theme = Theme::default();
+    let atlas = load_atlas(&theme).unwrap();
+    let frame = Frame {
+        rules: Rules {
+            width: 4,
+            height: 3,
+            walls: vec![Position { x: 3, y: 2 }],
+            ..default()
+        },
+        snake: vec![(Position { x: 0, y: 0 }, DirectionPair(Right, Right))],
+        food: vec![(Position { x: 2, y: 0 }, FoodKind::Normal)],
+        ..default()
+    };
+
+    let image = render(&frame, &theme, &atlas, 1);
+    let pixel = |x: u32, y: u32| image.get_pixel(x, y).0;
+    let color = |color: Color| color.as_rgba_f32().map(|c| (c * 255.0).round() as u8);
+
+    // a cell of border all round, 16 pixels a cell
+    assert_eq!(image.dimensions(), (6 * 16, 5 * 16));
+
+    // y goes up on the board and down in the image
+    assert_eq!(pixel(72, 24), color(theme.palette.outline));
+    assert_eq!(pixel(56, 56), color(theme.palette.food));
+    assert_eq!(pixel(40, 40), color(theme.palette.background));
+    assert_ne!(image.get_pixel(24, 56).0, color(theme.palette.background));
+
+    // twice the scale, twice the size
+    assert_eq!(render(&frame, &theme, &atlas, 2).dimensions(), (12 * 16, 10 * 16));
+}
+
+#[test]
+fn recorded_games_play_back_the_same() {
+    let mut app = app();
+
+    for (key, n) in [(KeyCode::Right, 4), (KeyCode::Up, 6), (KeyCode::Left, 3), (KeyCode::Up, 2)] {
+        press(&mut app, key);
+
+        for _ in 0..n {
+            tick(&mut app);
+        }
+    }
+
+    let recorded = app.world.resource::<Recorder>().frames().collect::<Vec<_>>();
+    let game = app
+        .world
+        .resource::<Recorder>()
+        .game(app.world.resource::<Replay>())
+        .unwrap();
+
+    // a frame from before the first move, then one a tick
+    assert_eq!(recorded.len(), 16);
+
+    let replayed = replay_frames(&game);
+    assert!(replayed.len() >= recorded.len());
+    assert_eq!(replayed[..recorded.len()], recorded[..]);
+}
+
+#[test]
+fn recorded_games_play_back_at_their_own_speed() {
+    let mut app = app_in(GameMode::TimeAttack(1));
+
+    app.world.resource_mut::<Config>().game_speed = 50;
+    app.world.send_event(NewGameEvent);
+    app.update();
+
+    press(&mut app, KeyCode::Up);
+
+    while !game_over(&app) {
+        tick(&mut app);
+    }
+
+    let recorded = app.world.resource::<Recorder>().frames().collect::<Vec<_>>();
+    let game = app
+        .world
+        .resource::<Recorder>()
+        .game(app.world.resource::<Replay>())
+        .unwrap();
+
+    assert_eq!(game.speeds, vec![(0, 50)]);
+    assert_eq!(replay_frames(&game), recorded);
+
+    // a speed change partway through goes back in on the same tick
+    let mut app = app_in(GameMode::TimeAttack(1));
+
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    tick(&mut app);
+    app.world.resource_mut::<Config>().game_speed = 50;
+
+    while !game_over(&app) {
+        tick(&mut app);
+    }
+
+    let recorded = app.world.resource::<Recorder>().frames().collect::<Vec<_>>();
+    let game = app
+        .world
+        .resource::<Recorder>()
+        .game(app.world.resource::<Replay>())
+        .unwrap();
+
+    assert_eq!(game.speeds, vec![(0, 100), (3, 50)]);
+    assert_eq!(replay_frames(&game), recorded);
+}
+
+#[test]
+fn recordings_take_every_tick_of_a_catch_up() {
+    let mut app = app();
+
+    press(&mut app, KeyCode::Up);
+    app.world.resource_mut::<ManualTicks>().0 = 4;
+    app.update();
+
+    let ticks = app.world.resource::<Round>().ticks;
+    let frames = app.world.resource::<Recorder>().frames().collect::<Vec<_>>();
+
+    assert_eq!(ticks, 4);
+    assert_eq!(frames.len() as u64, ticks + 1);
+
+    // a cell a tick, with the new head in each
+    let heads = frames.iter().map(|frame| frame.snake[0].0.y).collect::<Vec<_>>();
+    assert_eq!(heads, vec![3, 4, 5, 6, 7]);
+
+    // and a rewind takes them back off a tick at a time
+    press(&mut app, KeyCode::Back);
+    app.world.resource_mut::<ManualTicks>().0 = 2;
+    app.update();
+    assert_eq!(app.world.resource::<Recorder>().frames().count(), 3);
+}
+
+#[test]
+fn recordings_keep_up_with_walls_put_down_midgame() {
+    let mut app = app();
+
+    app.world
+        .resource_mut::<Scripts>()
+        .add("test.rhai", "fn on_eat(kind) { spawn_wall(10, 10); }");
+
+    go(&mut app, KeyCode::Up);
+    place_food(&mut app, 3, 5);
+    go(&mut app, KeyCode::Up);
+    go(&mut app, KeyCode::Up);
+
+    // nothing new while nothing moves
+    app.update();
+    app.update();
+
+    let wall = Position { x: 10, y: 10 };
+    let frames = app.world.resource::<Recorder>().frames().collect::<Vec<_>>();
+    let walled = frames.iter().map(|frame| frame.rules.walls.contains(&wall)).collect::<Vec<_>>();
+
+    assert_eq!(walled, vec![false, false, true, true]);
+}
+
+#[test]
+fn recordings_export_as_gifs_and_pngs() {
+    let mut app = app();
+
+    press(&mut app, KeyCode::Up);
+    step(&mut app, 3);
+
+    let frames = app.world.resource::<Recorder>().frames().collect::<Vec<_>>();
+    let theme = Theme::default();
+    let gif = std::env::temp_dir().join(format!("snek-export-{}.gif", std::process::id()));
+    let pngs = std::env::temp_dir().join(format!("snek-export-{}", std::process::id()));
+
+    write_frames(frames.clone(), &theme, &RecordSettings::default(), &gif).unwrap();
+    assert!(std::fs::read(&gif).unwrap().starts_with(b"GIF89a"));
+
+    let settings = RecordSettings {
+        format: RecordFormat::Png,
+        scale: 1,
+        ..default()
+    };
+    write_frames(frames.clone(), &theme, &settings, &pngs).unwrap();
+    assert_eq!(std::fs::read_dir(&pngs).unwrap().count(), frames.len());
+
+    let first = image::open(pngs.join("frame-00000.png")).unwrap();
+    assert_eq!((first.width(), first.height()), (22 * 16, 22 * 16));
+
+    std::fs::remove_file(&gif).ok();
+    std::fs::remove_dir_all(&pngs).ok();
+}
+
+#[test]
+fn export_settings_come_from_the_command_line() {
+    let args = |s: &str| parse_args(s.split_whitespace().map(String::from));
+
+    let parsed = args("--export game.ron --out game.gif --scale 3 --fps 12").unwrap();
+    assert_eq!(parsed.export, Some("game.ron".into()));
+    assert_eq!(parsed.settings.out, Some("game.gif".into()));
+    assert_eq!((parsed.settings.scale, parsed.settings.fps), (3, 12));
+    assert_eq!(parsed.settings.format, RecordFormat::Gif);
+
+    assert_eq!(args("--frames").unwrap().settings.format, RecordFormat::Png);
+    assert_eq!(args("").unwrap(), Default::default());
+    assert!(args("--scale 0").is_err());
+    assert!(args("--fps").is_err());
+    assert!(args("--loud").is_err());
+}
+
+#[test]
+fn screenshots_keep_the_score_and_mode() {
+    let mut app = app_in(GameMode::Zen);
+    let dir = std::env::temp_dir().join(format!("snek-screenshots-{}", std::process::id()));
+    app.world.resource_mut::<Screenshots>().dir = Some(dir.clone());
+
+    place_food(&mut app, 3, 4);
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    press(&mut app, KeyCode::F12);
+    app.update();
+
+    let path = app.world.resource::<Screenshots>().last.clone().unwrap();
+    assert!(path.starts_with(&dir));
+
+    let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+    let reader = decoder.read_info().unwrap();
+    let info = reader.info();
+    let text = |keyword: &str| {
+        info.uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == keyword)
+            .map(|chunk| chunk.text.clone())
+    };
+
+    assert_eq!(text("Score"), Some("1".to_string()));
+    assert_eq!(text("Mode"), Some("zen".to_string()));
+    assert_eq!((info.width, info.height), (22 * 32, 22 * 32));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn failed_screenshots_say_so() {
+    let mut app = app();
+    // a file where the folder should be, so the folder can't be made
+    let file = std::env::temp_dir().join(format!("snek-not-a-dir-{}", std::process::id()));
+    std::fs::write(&file, "").unwrap();
+    app.world.resource_mut::<Screenshots>().dir = Some(file.join("shots"));
+
+    press(&mut app, KeyCode::F12);
+    app.update();
+
+    assert_eq!(app.world.resource::<Screenshots>().last, None);
+    let toasts = &app.world.resource::<Stats>().toasts;
+    assert_eq!(toasts.len(), 1);
+    assert!(toasts[0].starts_with("couldn't save "));
+
+    std::fs::remove_file(&file).ok();
+}
+
+// puts a board from the text notation on in place of the game, like resuming a save
+fn load_board(app: &mut App, text: &str) {
+    let board = parse_board(text).unwrap();
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        config: app.world.resource::<Config>().clone(),
+        round: Round {
+            mode: app.world.resource::<Round>().mode,
+            rules: board.rules,
+            ..default()
+        },
+        snake: board.snake,
+        food: board.food,
+        creatures: board.creatures,
+        score: board.score,
+        step: TickClock::default().step,
+        rng: GameRng::new(0),
+        replay: Replay::default(),
+    };
+
+    app.world
+        .resource_mut::<Events<ResumeEvent>>()
+        .send(ResumeEvent(Box::new(save)));
+    app.update();
+}
+
+#[test]
+fn boards_read_and_print_as_text() {
+    let text = "
+        board 6x4 score 7
+        .0..$.
+        .>>R?.
+        .^..#0
+        .^..*m
+        custom 4,2 9 5
+        creature 5,3 Patrol(path:[(x:5,y:3)],target:0,backwards:false)
+    ";
+
+    let board = parse_board(text).unwrap();
+
+    assert_eq!((board.rules.width, board.rules.height, board.score), (6, 4, 7));
+    assert_eq!(board.rules.walls, vec![Position { x: 4, y: 1 }]);
+    assert_eq!(board.rules.portals, vec![(Position { x: 1, y: 3 }, Position { x: 5, y: 1 })]);
+    assert_eq!(
+        board.snake,
+        vec![
+            (Position { x: 3, y: 2 }, DirectionPair(Right, Right)),
+            (Position { x: 2, y: 2 }, DirectionPair(Right, Right)),
+            (Position { x: 1, y: 2 }, DirectionPair(Up, Right)),
+            (Position { x: 1, y: 1 }, DirectionPair(Up, Up)),
+            (Position { x: 1, y: 0 }, DirectionPair(Up, Up)),
+        ]
+    );
+    assert!(board.food.contains(&(Position { x: 4, y: 2 }, FoodKind::Custom { id: 9, points: 5 })));
+    assert!(board.food.contains(&(Position { x: 5, y: 0 }, FoodKind::Mouse)));
+    assert_eq!(board.food.len(), 4);
+    assert_eq!(board.creatures.len(), 1);
+
+    // the patrol's drawn in where it is
+    let printed = print_board(&board);
+    assert_eq!(printed.lines().nth(1), Some(".0..$B"));
+    assert_eq!(parse_board(&printed).unwrap(), board);
+
+    assert!(parse_board("board 2x2\n..\n.").is_err());
+    assert!(parse_board("board 2x2\nUU\n..").is_err());
+    assert!(parse_board("board 2x2\n?.\n..").is_err());
+    assert!(parse_board("board 3x1\n>.R").is_err());
+}
+
+#[test]
+fn games_print_as_boards() {
+    let mut app = app();
+
+    press(&mut app, KeyCode::Right);
+    step(&mut app, 2);
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+
+    let frame = app.world.resource::<Recorder>().frames().last().unwrap();
+    let board = parse_board(&print_board(&frame)).unwrap();
+
+    // everything but which way the pieces came in, which is worked out again
+    let cells = |frame: &Frame| frame.snake.iter().map(|(pos, dir)| (*pos, dir.1)).collect::<Vec<_>>();
+    assert_eq!(cells(&board), cells(&frame));
+    assert_eq!(board.food, frame.food);
+    assert_eq!(board.rules, frame.rules);
+}
+
+#[test]
+fn boards_make_fixtures() {
+    let mut app = app();
+
+    // about to bite its own tail
+    load_board(
+        &mut app,
+        "
+        board 6x4
+        .>>v..
+        .^L<..
+        ......
+        ......
+        ",
+    );
+    assert_eq!(length(&app), 6);
+
+    tick(&mut app);
+    assert_eq!(length(&app), 5);
+    assert_eq!(head(&mut app), Position { x: 2, y: 2 });
+}
+
+// the puzzle mode, on a puzzle made up for the test. the first food's down
+// before the snake moves, same as the game's idle ticks would do
+fn puzzle_app(text: &str) -> App {
+    let mut app = app_in(GameMode::Puzzle);
+
+    app.insert_resource(Puzzles {
+        list: vec![parse_puzzle("test", text).unwrap()],
+        current: 0,
+    });
+    app.world.send_event(NewGameEvent);
+    app.update();
+    tick(&mut app);
+
+    app
+}
+
+const TEST_PUZZLE: &str = "
+    moves 8
+    food 4,3 1,3
+    board 6x5
+    ......
+    ......
+    ..##..
+    .@....
+    .o....
+";
+
+fn key_for(dir: SnakeDirection) -> KeyCode {
+    match dir {
+        Up => KeyCode::Up,
+        Down => KeyCode::Down,
+        Left => KeyCode::Left,
+        _ => KeyCode::Right,
+    }
+}
+
+#[test]
+fn shipped_puzzles_are_solvable() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/puzzles");
+
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let puzzle = read_puzzle(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let moves = solve(&puzzle.rules).unwrap_or_else(|e| panic!("{}: {}", puzzle.name, e));
+
+        assert!(
+            puzzle.rules.move_limit.is_some_and(|limit| moves.len() as u64 <= limit),
+            "{}: {} moves at best, but the limit's {:?}",
+            puzzle.name,
+            moves.len(),
+            puzzle.rules.move_limit
+        );
+    }
+}
+
+#[test]
+fn puzzles_are_played_the_way_theyre_solved() {
+    let mut app = puzzle_app(TEST_PUZZLE);
+    let rules = app.world.resource::<Round>().rules.clone();
+    let moves = solve(&rules).unwrap();
+
+    // over the wall would leave it facing away from the second one,
+    // so it's under the wall and up
+    assert_eq!(moves.len(), 8);
+    assert_eq!(head(&mut app), Position { x: 1, y: 1 });
+    assert_eq!(food(&mut app), vec![Position { x: 4, y: 3 }]);
+
+    for dir in moves {
+        press(&mut app, key_for(dir));
+        tick(&mut app);
+    }
+
+    assert_eq!(score(&app), 2);
+    assert_eq!(app.world.resource::<GameOver>().0, Some(crate::Outcome::Won));
+
+    // each puzzle has its own best
+    let high_scores = app.world.resource::<HighScores>();
+    assert_eq!(high_scores.best("puzzle: test"), 2);
+    assert_eq!(high_scores.best("puzzle"), 0);
+}
+
+#[test]
+fn puzzles_run_out_of_moves() {
+    let mut app = puzzle_app(TEST_PUZZLE);
+
+    for (key, n) in [(KeyCode::Right, 4), (KeyCode::Up, 3)] {
+        press(&mut app, key);
+        for _ in 0..n {
+            tick(&mut app);
+        }
+    }
+    assert!(!game_over(&app));
+    assert_eq!(app.world.resource::<Round>().moves_left(), Some(1));
+
+    press(&mut app, KeyCode::Left);
+    tick(&mut app);
+    assert_eq!(app.world.resource::<GameOver>().0, Some(crate::Outcome::OutOfMoves));
+
+    // one short of the best can't be done at all
+    let mut rules = app.world.resource::<Round>().rules.clone();
+    rules.move_limit = Some(7);
+    assert!(solve(&rules).is_err());
+}
+
+fn decorations(app: &mut App) -> usize {
+    app.world.query::<&Decoration>().iter(&app.world).count()
+}
+
+#[test]
+fn accessibility_settings_change_the_look() {
+    let mut app = app();
+
+    {
+        let mut config = app.world.resource_mut::<Config>();
+        config.color_assist = ColorAssist::RedGreen;
+        config.high_contrast = true;
+        config.food_markers = true;
+    }
+
+    // the first food goes down
+    tick(&mut app);
+
+    let palette = app.world.resource::<Theme>().palette;
+    assert_eq!(palette.background, Color::BLACK);
+    assert_eq!(palette.food, Color::rgb(0.902, 0.624, 0.0));
+    assert_eq!(palette.segment_tint.a(), 1.0);
+
+    // outlines on the food and head, and the food's dot
+    assert_eq!(decorations(&mut app), 3);
+
+    app.world.resource_mut::<Config>().food_markers = false;
+    app.update();
+    app.update();
+    assert_eq!(decorations(&mut app), 2);
+
+    // back to the theme as it came
+    {
+        let mut config = app.world.resource_mut::<Config>();
+        config.color_assist = ColorAssist::Off;
+        config.high_contrast = false;
+    }
+    app.update();
+    app.update();
+    assert_eq!(app.world.resource::<Theme>().palette, Theme::default().palette);
+    assert_eq!(decorations(&mut app), 0);
+}
+
+#[test]
+fn game_speed_slows_the_snake_down() {
+    let mut app = app();
+
+    go(&mut app, KeyCode::Up);
+    assert_eq!(app.world.resource::<TickClock>().step, Duration::from_millis(125));
+
+    app.world.resource_mut::<Config>().game_speed = 50;
+    go(&mut app, KeyCode::Up);
+    assert_eq!(app.world.resource::<TickClock>().step, Duration::from_millis(250));
+}
+
+#[test]
+fn languages_fall_back_to_english() {
+    let mut locale = Locale::default();
+    let text = "language.name = Test\n# a comment\n\nmenu.resume = onwards\nbanner.best = top: {best}\n";
+
+    locale.list.push(parse_language("xx", text).unwrap());
+    assert!(parse_language("xx", "menu.resume onwards").is_err());
+
+    locale.select(1);
+    assert_eq!(locale.language().name, "Test");
+    assert_eq!(locale.get("menu.resume"), "onwards");
+    assert_eq!(locale.format("banner.best", &[("best", "12".to_string())]), "top: 12");
+
+    // missing from the language, then missing altogether
+    assert_eq!(locale.get("menu.settings"), "settings");
+    assert_eq!(locale.get("menu.nonsense"), "menu.nonsense");
+
+    // no fonts listed, so the default
+    assert_eq!(locale.font(), "FiraMono-Regular.ttf");
+}
+
+#[test]
+fn languages_need_one_of_their_fonts() {
+    let mut locale = Locale::default();
+    let text = "language.font = NotThere.ttf, FiraMono-Regular.ttf\n";
+
+    // the first one there is
+    locale.add(parse_language("xx", text).unwrap()).unwrap();
+    locale.select(1);
+    assert_eq!(locale.font(), "FiraMono-Regular.ttf");
+
+    // and none there at all is left out, rather than drawn as boxes
+    let missing = parse_language("yy", "language.font = NotThere.ttf\n").unwrap();
+    assert!(locale.add(missing).is_err());
+    assert_eq!(locale.list.len(), 2);
+
+    // no cjk font ships, so japanese waits for one to be dropped in
+    let japanese = parse_language("ja", &std::fs::read_to_string("assets/lang/ja.txt").unwrap());
+    assert!(!japanese.unwrap().fonts.iter().any(|font| font == "FiraMono-Regular.ttf"));
+}
+
+// every shipped language only has keys english has, with the same {placeholders}
+#[test]
+fn shipped_languages_match_english() {
+    let placeholders = |text: &str| {
+        let mut names = text
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name.to_string()))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    let english = parse_language("en", &std::fs::read_to_string("assets/lang/en.txt").unwrap()).unwrap();
+
+    for code in ["es", "ja"] {
+        let path = format!("assets/lang/{}.txt", code);
+        let language = parse_language(code, &std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert!(!language.fonts.is_empty(), "{} lists no font", code);
+
+        for key in language.keys() {
+            let theirs = language.get(key).unwrap();
+            let ours = english.get(key).unwrap_or_else(|| panic!("{} has a stray key {}", code, key));
+
+            assert_eq!(placeholders(theirs), placeholders(ours), "{} {}", code, key);
+        }
+    }
+}
+
+#[test]
+fn switching_language_relabels_things() {
+    let mut app = app();
+    let text = "language.name = Test\nmode.classic = klassik\nmode.time_attack = {secs} sekunden\n";
+
+    app.world
+        .resource_mut::<Locale>()
+        .list
+        .push(parse_language("xx", text).unwrap());
+
+    app.world.send_event(MenuEvent::Adjust("language", 1));
+    app.update();
+
+    let locale = app.world.resource::<Locale>();
+    assert_eq!(app.world.resource::<Config>().language, "xx");
+    assert_eq!(GameMode::Classic.label(locale), "klassik");
+    assert_eq!(GameMode::TimeAttack(60).label(locale), "60 sekunden");
+    assert_eq!(GameMode::Zen.label(locale), "zen");
+
+    // and round again to english
+    app.world.send_event(MenuEvent::Adjust("language", -1));
+    app.update();
+
+    assert_eq!(app.world.resource::<Config>().language, "en");
+    assert_eq!(GameMode::Classic.label(app.world.resource::<Locale>()), "classic");
+}
+
+fn hud_text(app: &mut App, item: HudItem) -> String {
+    app.world
+        .query::<(&HudItem, &Text)>()
+        .iter(&app.world)
+        .find(|(found, _)| **found == item)
+        .map(|(_, text)| text.sections[0].value.clone())
+        .unwrap()
+}
+
+#[test]
+fn hud_shows_the_game() {
+    let mut app = app();
+
+    place_food(&mut app, 3, 4);
+    press(&mut app, KeyCode::Up);
+    tick(&mut app);
+    // the hud's drawn before the tick, so it's a frame behind
+    app.update();
+
+    assert_eq!(hud_text(&mut app, HudItem::Score), "score 1");
+    assert_eq!(hud_text(&mut app, HudItem::Best), "best 1");
+    assert_eq!(hud_text(&mut app, HudItem::Mode), "classic");
+    assert_eq!(hud_text(&mut app, HudItem::Length), "length 4");
+    // a little faster for having eaten
+    let clock_step = app.world.resource::<TickClock>().step;
+    assert_eq!(
+        hud_text(&mut app, HudItem::Speed),
+        format!("{:.1} tiles/s", 1.0 / clock_step.as_secs_f32())
+    );
+    assert_eq!(hud_text(&mut app, HudItem::Time), "0:00");
+    assert_eq!(hud_text(&mut app, HudItem::PowerUps), "");
+
+    // power-ups count down with the game and go when they run out
+    let until = app.world.resource::<Round>().elapsed + Duration::from_millis(200);
+    app.world.resource_mut::<PowerUps>().push(PowerUp {
+        name: "ghost".to_string(),
+        until,
+    });
+    app.update();
+    assert_eq!(hud_text(&mut app, HudItem::PowerUps), "ghost 1s");
+
+    step(&mut app, 2);
+    app.update();
+    assert!(app.world.resource::<PowerUps>().is_empty());
+    assert_eq!(hud_text(&mut app, HudItem::PowerUps), "");
+
+    // the big number behind the board is off unless it's asked for
+    let big_score = |app: &mut App| {
+        app.world
+            .query_filtered::<&Visibility, With<ScoreText>>()
+            .single(&app.world)
+            .is_visible
+    };
+
+    assert!(!big_score(&mut app));
+    app.world.send_event(MenuEvent::Activate("big_score"));
+    app.update();
+    app.update();
+    assert!(big_score(&mut app));
+}
+
+#[test]
+fn hud_shows_time_and_moves_left() {
+    let mut app = puzzle_app(TEST_PUZZLE);
+
+    go(&mut app, KeyCode::Right);
+    app.update();
+    assert_eq!(hud_text(&mut app, HudItem::Mode), "puzzle");
+    assert_eq!(hud_text(&mut app, HudItem::Time), "0:00  7 moves");
+
+    let mut app = app_in(GameMode::TimeAttack(60));
+
+    go(&mut app, KeyCode::Up);
+    app.update();
+    assert_eq!(hud_text(&mut app, HudItem::Mode), "time attack 60s");
+    assert_eq!(hud_text(&mut app, HudItem::Time), "1:00 left");
+}
@@ -0,0 +1,403 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use serde::Deserialize;
+use std::{fs, path::PathBuf};
+
+use crate::{
+    config::Config,
+    locale::Locale,
+    menu::{Menu, MenuEntries, MenuEvent, MenuPage},
+    DirectionPair, Food, FoodKind, Outline, ScoreText, SnakeDirection, SnakeSegments, SpriteSheet,
+};
+
+// compiled in so there's always something to draw with, even with no themes folder
+const DEFAULT_THEME: &str = include_str!("../assets/themes/default.ron");
+const THEME_DIR: &str = "assets/themes";
+
+// the arguments to TextureAtlas::from_grid_with_padding
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AtlasLayout {
+    pub image: String,
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    pub padding: (f32, f32),
+    pub offset: (f32, f32),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct DirectionalTiles {
+    pub up: usize,
+    pub down: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl DirectionalTiles {
+    pub fn get(&self, dir: SnakeDirection) -> usize {
+        use SnakeDirection::*;
+
+        match dir {
+            Up | Null => self.up,
+            Down => self.down,
+            Left => self.left,
+            Right => self.right,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct BodyTiles {
+    pub vertical: usize,
+    pub horizontal: usize,
+}
+
+// named after the two sides of the tile the corner connects
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct CornerTiles {
+    pub up_right: usize,
+    pub up_left: usize,
+    pub down_right: usize,
+    pub down_left: usize,
+}
+
+// atlas indices for every part of the snake
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct ThemeTiles {
+    pub head: DirectionalTiles,
+    pub eating: DirectionalTiles,
+    pub body: BodyTiles,
+    pub corners: CornerTiles,
+    pub tail: DirectionalTiles,
+    // None means food is a plain square in palette.food
+    pub food: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnakePart {
+    Head,
+    Body,
+    Tail,
+}
+
+impl ThemeTiles {
+    pub fn part(&self, part: SnakePart, dir: DirectionPair) -> usize {
+        match part {
+            SnakePart::Head => self.head.get(dir.1),
+            SnakePart::Body => self.segment(dir),
+            // the tail only connects on one side, the way it's heading
+            SnakePart::Tail => self.tail.get(dir.1),
+        }
+    }
+
+    // picks the body or corner tile for a dirpair (see DirectionPair for what the pair means)
+    pub fn segment(&self, dir: DirectionPair) -> usize {
+        use SnakeDirection::*;
+
+        match (dir.0, dir.1) {
+            (Left, Up) | (Down, Right) => self.corners.up_right,
+
+            (Right, Up) | (Down, Left) => self.corners.up_left,
+
+            (Left, Down) | (Up, Right) => self.corners.down_right,
+
+            (Right, Down) | (Up, Left) => self.corners.down_left,
+
+            (Up, Up) | (Down, Down) => self.body.vertical,
+
+            (Left, Left) | (Right, Right) => self.body.horizontal,
+
+            _ => self.body.vertical,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    pub food: Color,
+    pub outline: Color,
+    pub score_text: Color,
+    pub head_tint: Color,
+    pub segment_tint: Color,
+    // older theme files don't have one, they get gold
+    #[serde(default = "default_bonus_food")]
+    pub bonus_food: Color,
+    // the same goes for the creatures some levels have
+    #[serde(default = "default_mouse")]
+    pub mouse: Color,
+    #[serde(default = "default_rival")]
+    pub rival: Color,
+    #[serde(default = "default_portal")]
+    pub portal: Color,
+}
+
+fn default_bonus_food() -> Color {
+    Color::GOLD
+}
+
+fn default_mouse() -> Color {
+    Color::GRAY
+}
+
+fn default_rival() -> Color {
+    Color::ORANGE_RED
+}
+
+fn default_portal() -> Color {
+    Color::PURPLE
+}
+
+impl Palette {
+    pub fn tint(&self, part: SnakePart) -> Color {
+        ifelse!(part == SnakePart::Head, self.head_tint, self.segment_tint)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub atlas: AtlasLayout,
+    pub tiles: ThemeTiles,
+    pub palette: Palette,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_THEME).expect("built-in theme should parse")
+    }
+}
+
+// every theme found on disk, in menu order, and which one is in use
+pub struct Themes {
+    pub list: Vec<Theme>,
+    pub current: usize,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Themes {
+            list: vec![Theme::default()],
+            current: 0,
+        }
+    }
+}
+
+// default.ron goes first, the rest alphabetically. broken files are skipped with a warning
+fn load_themes() -> Themes {
+    let dir = FileAssetIo::get_base_path().join(THEME_DIR);
+
+    let mut paths = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+
+    paths.sort_by_key(|path| (!path.ends_with("default.ron"), path.clone()));
+
+    let list = paths
+        .iter()
+        .filter_map(|path| {
+            let theme = fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| ron::from_str::<Theme>(&s).map_err(|e| e.to_string()));
+
+            match theme {
+                Ok(theme) => Some(theme),
+                Err(e) => {
+                    warn!("skipping theme {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect::<Vec<Theme>>();
+
+    ifelse!(list.is_empty(), Themes::default(), Themes { list, current: 0 })
+}
+
+// the one the config asks for, for drawing without the app running
+pub fn saved_theme(config: &Config) -> Theme {
+    load_themes()
+        .list
+        .into_iter()
+        .find(|theme| theme.name == config.theme)
+        .unwrap_or_default()
+}
+
+pub fn build_atlas(
+    layout: &AtlasLayout,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+) -> Handle<TextureAtlas> {
+    let texture_handle = asset_server.load(layout.image.as_str());
+    let atlas = TextureAtlas::from_grid_with_padding(
+        texture_handle,
+        Vec2::new(layout.tile_size.0, layout.tile_size.1),
+        layout.columns,
+        layout.rows,
+        Vec2::new(layout.padding.0, layout.padding.1),
+        Vec2::new(layout.offset.0, layout.offset.1),
+    );
+    texture_atlases.add(atlas)
+}
+
+// food is either a plain sprite or an atlas tile depending on the theme,
+// so swap whichever one the entity has for the right one.
+// bonus food is the same thing in a different colour
+pub fn insert_food_sprite(
+    entity: &mut bevy::ecs::system::EntityCommands,
+    kind: FoodKind,
+    theme: &Theme,
+    sprite_sheet: &SpriteSheet,
+) {
+    entity
+        .remove::<Sprite>()
+        .remove::<Handle<Image>>()
+        .remove::<TextureAtlasSprite>()
+        .remove::<Handle<TextureAtlas>>();
+
+    match theme.tiles.food {
+        Some(index) => entity
+            .insert(TextureAtlasSprite {
+                index,
+                color: match kind {
+                    FoodKind::Normal | FoodKind::Custom { .. } => Color::WHITE,
+                    FoodKind::Bonus => theme.palette.bonus_food,
+                    FoodKind::Mouse => theme.palette.mouse,
+                },
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            })
+            .insert(sprite_sheet.0.clone()),
+        None => entity
+            .insert(Sprite {
+                color: match kind {
+                    FoodKind::Normal | FoodKind::Custom { .. } => theme.palette.food,
+                    FoodKind::Bonus => theme.palette.bonus_food,
+                    FoodKind::Mouse => theme.palette.mouse,
+                },
+                ..default()
+            })
+            .insert(Handle::<Image>::default()),
+    };
+}
+
+fn make_atlas(
+    theme: Res<Theme>,
+    mut sprite_sheet: ResMut<SpriteSheet>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    sprite_sheet.0 = build_atlas(&theme.atlas, &asset_server, &mut texture_atlases);
+}
+
+pub(crate) fn theme_menu(
+    menu: Res<Menu>,
+    mut entries: ResMut<MenuEntries>,
+    mut reader: EventReader<MenuEvent>,
+    mut themes: ResMut<Themes>,
+    mut theme: ResMut<Theme>,
+    mut config: ResMut<Config>,
+    mut sprite_sheet: ResMut<SpriteSheet>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    locale: Res<Locale>,
+) {
+    for event in reader.iter() {
+        let step = match event {
+            MenuEvent::Adjust("theme", step) => *step,
+            MenuEvent::Activate("theme") => 1,
+            _ => continue,
+        };
+
+        let count = themes.list.len() as i32;
+        themes.current = (themes.current as i32 + step).rem_euclid(count) as usize;
+
+        let next = themes.list[themes.current].clone();
+
+        if next.atlas != theme.atlas {
+            sprite_sheet.0 = build_atlas(&next.atlas, &asset_server, &mut texture_atlases);
+        }
+
+        config.theme = next.name.clone();
+        *theme = next;
+    }
+
+    if menu.open {
+        entries.set(
+            MenuPage::Settings,
+            "theme",
+            40,
+            locale.format("settings.theme", &[("name", theme.name.clone())]),
+        );
+    }
+}
+
+// pushes the current theme onto everything that's already spawned
+pub(crate) fn apply_theme(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    sprite_sheet: Res<SpriteSheet>,
+    mut clear_color: ResMut<ClearColor>,
+    segments: Res<SnakeSegments>,
+    mut sprites: Query<(&mut TextureAtlasSprite, &mut Handle<TextureAtlas>, &DirectionPair)>,
+    food: Query<(Entity, &FoodKind), With<Food>>,
+    mut outline: Query<&mut Sprite, With<Outline>>,
+    mut score_text: Query<&mut Text, With<ScoreText>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    clear_color.0 = theme.palette.background;
+
+    for (i, segment) in segments.iter().enumerate() {
+        if let Ok((mut sprite, mut handle, dir)) = sprites.get_mut(*segment) {
+            let part = segments.part(i);
+
+            *handle = sprite_sheet.0.clone();
+            sprite.index = theme.tiles.part(part, *dir);
+            sprite.color = theme.palette.tint(part);
+        }
+    }
+
+    for (ent, kind) in food.iter() {
+        insert_food_sprite(&mut commands.entity(ent), *kind, &theme, &sprite_sheet);
+    }
+
+    for mut sprite in &mut outline {
+        sprite.color = theme.palette.outline;
+    }
+
+    for mut text in &mut score_text {
+        for section in text.sections.iter_mut() {
+            section.style.color = theme.palette.score_text;
+        }
+    }
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        let mut themes = load_themes();
+
+        // pick up where the player left off, if that theme's still around
+        if let Some(config) = app.world.get_resource::<Config>() {
+            if let Some(i) = themes.list.iter().position(|t| t.name == config.theme) {
+                themes.current = i;
+            }
+        }
+
+        let theme = themes.list[themes.current].clone();
+
+        app.insert_resource(ClearColor(theme.palette.background))
+            .insert_resource(theme)
+            .insert_resource(themes)
+            .add_startup_system(make_atlas)
+            .add_system(theme_menu)
+            .add_system(apply_theme.after(theme_menu));
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::{menu::Menu, rewind::Rewind, GameOver, SNAKE_STEP};
+
+// most ticks a single frame is allowed to make up for. after a long hitch the snake
+// jumps ahead this far and the rest is forgotten, instead of fast-forwarding for seconds
+pub const MAX_CATCH_UP: u32 = 5;
+
+/// the label the tick stage is added under, for putting your own systems in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, StageLabel)]
+pub struct TickStageLabel;
+
+/// how many ticks the game has run. only ever goes up by one per tick,
+/// so anything that wants to line up with the simulation (replays, bots, netcode) can count on it
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick(pub u64);
+
+/// frame time goes in, whole ticks come out
+#[derive(Debug, Clone, Copy)]
+pub struct TickClock {
+    /// how long a tick lasts right now, the snake speeds up by shortening this
+    pub step: Duration,
+    accumulator: Duration,
+}
+
+impl Default for TickClock {
+    fn default() -> Self {
+        TickClock {
+            step: Duration::from_millis(SNAKE_STEP as u64),
+            accumulator: Duration::ZERO,
+        }
+    }
+}
+
+impl TickClock {
+    // banks a frame's worth of time and hands back how many ticks are due
+    pub fn advance(&mut self, delta: Duration) -> u32 {
+        self.accumulator += delta;
+
+        let step = self.step.max(Duration::from_millis(1));
+        let due = (self.accumulator.as_nanos() / step.as_nanos()).min(u32::MAX as u128) as u32;
+
+        if due > MAX_CATCH_UP {
+            self.accumulator = Duration::ZERO;
+            MAX_CATCH_UP
+        } else {
+            self.accumulator -= step * due;
+            due
+        }
+    }
+
+    // throws away banked time, so coming back from a pause doesn't count the pause
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+    }
+}
+
+// when this is around, the clock is ignored and the snake only moves when it's told to.
+// the tests drive the game this way
+#[derive(Default)]
+pub struct ManualTicks(pub u32);
+
+/// runs the gameplay systems once per tick, however many ticks the frame owes.
+/// each tick is a full stage run, so commands (new segments, eaten food) land before the next one
+pub struct TickStage {
+    stage: SystemStage,
+    // what a tick runs instead while the player's rewinding
+    rewind: SystemStage,
+}
+
+impl Default for TickStage {
+    fn default() -> Self {
+        TickStage {
+            stage: SystemStage::parallel(),
+            rewind: SystemStage::parallel(),
+        }
+    }
+}
+
+impl TickStage {
+    pub fn add_system_set(&mut self, system_set: SystemSet) -> &mut Self {
+        self.stage.add_system_set(system_set);
+        self
+    }
+
+    pub fn add_rewind_system_set(&mut self, system_set: SystemSet) -> &mut Self {
+        self.rewind.add_system_set(system_set);
+        self
+    }
+}
+
+fn rewinding(world: &World) -> bool {
+    world.get_resource::<Rewind>().is_some_and(|rewind| rewind.active)
+}
+
+// the game holds still while the menu is open, and stops once it's over.
+// rewinding is the one way back out of a game over
+fn paused(world: &World) -> bool {
+    world.resource::<Menu>().open || (world.resource::<GameOver>().0.is_some() && !rewinding(world))
+}
+
+impl Stage for TickStage {
+    fn run(&mut self, world: &mut World) {
+        if paused(world) {
+            world.resource_mut::<TickClock>().reset();
+            return;
+        }
+
+        let due = match world.get_resource_mut::<ManualTicks>() {
+            Some(mut manual) => std::mem::take(&mut manual.0),
+            None => {
+                let delta = world.resource::<Time>().delta();
+                world.resource_mut::<TickClock>().advance(delta)
+            }
+        };
+
+        for _ in 0..due {
+            // dying halfway through a catch-up ends it there
+            if paused(world) {
+                break;
+            }
+
+            // going backwards doesn't count as a tick
+            if rewinding(world) {
+                self.rewind.run(world);
+            } else {
+                self.stage.run(world);
+                world.resource_mut::<Tick>().0 += 1;
+            }
+        }
+    }
+}
+
+/// the tick stage and the clock that drives it
+pub struct TickPlugin;
+
+impl Plugin for TickPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tick::default())
+            .insert_resource(TickClock::default())
+            .add_stage_after(CoreStage::Update, TickStageLabel, TickStage::default());
+    }
+}